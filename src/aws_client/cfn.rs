@@ -1,8 +1,9 @@
-use anyhow::Result;
-use serde_json::{json, Value};
 use tracing::{info, instrument};
 
-use super::ServerUpdater;
+use super::{ServerUpdater, StackInfo};
+use crate::error::{Error, Result};
+use crate::model::domain::ServerDefinition;
+use crate::model::domain::{InstanceSize, StackFailure, StackStatus, UpdateOutcome};
 
 use aws_sdk_cloudformation::{
     error::{ProvideErrorMetadata, SdkError},
@@ -16,28 +17,24 @@ pub struct CfnAccessor {
 
 #[derive(Debug)]
 enum ServerState {
-    Running(String),
+    /// Running the save in the mount dir, on a new instance size if one is given.
+    Running(String, Option<InstanceSize>),
     Stopped,
 }
 
 impl ServerState {
     fn as_template_value(&self) -> &str {
         match self {
-            ServerState::Running(_) => "Running",
+            ServerState::Running(..) => "Running",
             ServerState::Stopped => "Stopped",
         }
     }
 }
 
-enum UpdateResponse<'a> {
-    Success,
-    HandledError(&'a str),
-}
-
 impl CfnAccessor {
-    pub fn new(config: &aws_config::SdkConfig) -> Self {
+    pub fn new(sdk_config: &aws_config::SdkConfig) -> Self {
         CfnAccessor {
-            client: aws_sdk_cloudformation::Client::new(config),
+            client: aws_sdk_cloudformation::Client::new(sdk_config),
         }
     }
 
@@ -47,40 +44,65 @@ impl CfnAccessor {
         "EnableRcon",
         "FactorioImageTag",
         "HostedZoneId",
-        "InstanceType",
         "KeyPairName",
         "RecordName",
-        "SpotPrice",
         "UpdateModsOnStart",
         "YourIp",
     ];
 
-    /// Updates the factorio CFN template to put the server in the desired state.
+    /// Updates the server's CFN stack to put it in the desired state.
     ///
     /// If the server is already in the desired state, or an update is already
-    /// in progress, no change is made and `UpdateResponse::HandledError` is returned.
+    /// in progress, no change is made and the matching `UpdateOutcome` is returned.
+    ///
+    /// CloudFormation tags every event of the update with `request_token`, which
+    /// is how the update-complete Lambda finds the interaction that made it.
     #[instrument]
-    async fn update_server(&self, desired_state: ServerState) -> Result<UpdateResponse<'static>> {
+    async fn update_server(
+        &self,
+        server: &ServerDefinition,
+        desired_state: ServerState,
+        request_token: Option<&str>,
+    ) -> Result<UpdateOutcome> {
         info!("attempting to update server");
 
-        let unchanged_params: Vec<Parameter> = Self::UNCHANGED_PARAMS
+        let previous = |name: &str| {
+            Parameter::builder()
+                .set_parameter_key(Some(name.to_string()))
+                .set_use_previous_value(Some(true))
+                .build()
+        };
+        let value = |name: &str, value: &str| {
+            Parameter::builder()
+                .set_parameter_key(Some(name.to_string()))
+                .set_parameter_value(Some(value.to_string()))
+                .build()
+        };
+
+        let mut params: Vec<Parameter> = Self::UNCHANGED_PARAMS
             .iter()
-            .map(|name| {
-                Parameter::builder()
-                    .set_parameter_key(Some(name.to_string()))
-                    .set_use_previous_value(Some(true))
-                    .build()
-            })
+            .map(|name| previous(name))
             .collect();
+        match &desired_state {
+            ServerState::Running(_, Some(size)) => {
+                params.push(value("InstanceType", &size.instance_type));
+                params.push(value("SpotPrice", &size.spot_price));
+            }
+            _ => {
+                params.push(previous("InstanceType"));
+                params.push(previous("SpotPrice"));
+            }
+        }
 
         info!("updating server");
         let mut builder = self
             .client
             .update_stack()
-            .stack_name("factorio-ecs-spot")
+            .stack_name(&server.stack_name)
             .use_previous_template(true)
             .capabilities(aws_sdk_cloudformation::types::Capability::CapabilityIam)
-            .set_parameters(Some(unchanged_params))
+            .set_client_request_token(request_token.map(str::to_string))
+            .set_parameters(Some(params))
             .parameters(
                 Parameter::builder()
                     .set_parameter_key(Some("ServerState".to_string()))
@@ -88,7 +110,7 @@ impl CfnAccessor {
                     .build(),
             );
 
-        if let ServerState::Running(mount_dir) = desired_state {
+        if let ServerState::Running(mount_dir, _) = desired_state {
             builder = builder.parameters(
                 Parameter::builder()
                     .set_parameter_key(Some("MountingDir".to_string()))
@@ -97,103 +119,164 @@ impl CfnAccessor {
             )
         }
 
-        let res = builder
-            .send()
-            .await
-            .map(|_| UpdateResponse::Success)
-            .unwrap_or_else(|sdk_error| {
+        match builder.send().await {
+            Ok(_) => Ok(UpdateOutcome::Updating),
+            Err(sdk_error) => {
                 tracing::error!(?sdk_error, "UpdateStackError");
 
-                if let SdkError::ServiceError(response_error) = sdk_error {
-                    let response_error = response_error.into_err();
-                    if ProvideErrorMetadata::code(&response_error) == Some("ValidationError") {
-                        let message = response_error.message().unwrap();
+                // Some expected outcomes are only reported as validation errors
+                if let SdkError::ServiceError(response_error) = &sdk_error {
+                    let response_error = response_error.err();
+                    if response_error.code() == Some("ValidationError") {
+                        let message = response_error.message().unwrap_or_default();
                         info!(message, "UpdateStack ValidationError");
                         if message == "No updates are to be performed." {
-                            return UpdateResponse::HandledError(
-                                "Server is already in the desired state.",
-                            );
+                            return Ok(UpdateOutcome::AlreadyInState);
                         } else if message
                             .contains("is in UPDATE_IN_PROGRESS state and can not be updated")
                         {
-                            return UpdateResponse::HandledError(
-                                "Server is currently being updated",
-                            );
+                            return Ok(UpdateOutcome::UpdateInProgress);
                         }
                     }
-                    panic!("Unhandled UpdateStackError {:?}", response_error);
-                } else {
-                    panic!("Unhandled SDK error: {:?}", sdk_error)
                 }
-            });
-
-        Ok(res)
+                Err(sdk_error.into())
+            }
+        }
     }
 }
 
 impl ServerUpdater for CfnAccessor {
-    async fn start_server(&self, mount_dir: &str) -> Result<Value> {
-        let res = self
-            .update_server(ServerState::Running(mount_dir.to_string()))
+    async fn start_server(
+        &self,
+        server: &ServerDefinition,
+        mount_dir: &str,
+        size: Option<&InstanceSize>,
+        request_token: &str,
+    ) -> Result<UpdateOutcome> {
+        self.update_server(
+            server,
+            ServerState::Running(mount_dir.to_string(), size.cloned()),
+            Some(request_token),
+        )
+        .await
+    }
+
+    async fn stop_server(
+        &self,
+        server: &ServerDefinition,
+        request_token: Option<&str>,
+    ) -> Result<UpdateOutcome> {
+        self.update_server(server, ServerState::Stopped, request_token)
+            .await
+    }
+}
+
+impl StackInfo for CfnAccessor {
+    async fn get_stack_status(&self, server: &ServerDefinition) -> Result<StackStatus> {
+        let response = self
+            .client
+            .describe_stacks()
+            .stack_name(&server.stack_name)
+            .send()
             .await?;
 
-         let (title, description) =match res {
-            UpdateResponse::Success => {
-                ("Starting the server!", Some(format!("Using the `{}` save. This message will update when the server is ready to join.", mount_dir)))
-            }
-            UpdateResponse::HandledError(msg) => (msg, None),
+        let Some(stack) = response.stacks().first() else {
+            return Err(Error::NotFound(format!("stack {}", server.stack_name)));
         };
 
-        Ok(json!({
-            "type": 4,
-            "data": {
-                "tts": false,
-                "content": "",
-                "embeds": [
-                    {
-                      "type": "rich",
-                      "title": title,
-                      "description": description,
-                      "color": 0x00FFFF,
-                      "thumbnail": {
-                        "url": "https://factorio.com/static/img/factorio-wheel.png",
-                        "height": 0,
-                        "width": 0
-                      }
-                    }
-                  ],
-                "allowed_mentions": { "parse": [] }
-            }
-        }))
+        let parameter = |key: &str| {
+            stack
+                .parameters()
+                .iter()
+                .find(|param| param.parameter_key() == Some(key))
+                .and_then(|param| param.parameter_value())
+                .filter(|value| !value.is_empty())
+                .map(|value| value.to_string())
+        };
+
+        Ok(StackStatus {
+            status: stack
+                .stack_status()
+                .map(|status| status.as_str().to_string())
+                .unwrap_or_default(),
+            server_state: parameter("ServerState"),
+            save: parameter("MountingDir").map(|dir| dir.trim_matches('/').to_string()),
+            instance_type: parameter("InstanceType"),
+            spot_price: parameter("SpotPrice"),
+            image_tag: parameter("FactorioImageTag"),
+        })
     }
 
-    async fn stop_server(&self) -> Result<Value> {
-        let res = self.update_server(ServerState::Stopped).await?;
+    /// Finds the first resource that failed during the stack's latest operation.
+    ///
+    /// Events are listed newest first, so the search stops at the event that
+    /// started the operation. Only the first page is read, which holds the
+    /// latest 100 events and is plenty for this template.
+    async fn get_last_failure(&self, server: &ServerDefinition) -> Result<Option<StackFailure>> {
+        let response = self
+            .client
+            .describe_stack_events()
+            .stack_name(&server.stack_name)
+            .send()
+            .await?;
 
-        let title = match res {
-            UpdateResponse::Success => "Stopping the server!",
-            UpdateResponse::HandledError(msg) => msg,
-        };
+        let mut failure = None;
+        for event in response.stack_events() {
+            let status = event
+                .resource_status()
+                .map(|status| status.as_str())
+                .unwrap_or_default();
+            let resource = event.logical_resource_id().unwrap_or_default();
+            let is_stack = resource == server.stack_name;
 
-        Ok(json!({
-            "type": 4,
-            "data": {
-                "tts": false,
-                "content": "",
-                "embeds": [
-                    {
-                      "type": "rich",
-                      "title": title,
-                      "color": 0x930707,
-                      "thumbnail": {
-                        "url": "https://factorio.com/static/img/factorio-wheel.png",
-                        "height": 0,
-                        "width": 0
-                      }
-                    }
-                  ],
-                "allowed_mentions": { "parse": [] }
+            if is_stack && (status == "UPDATE_IN_PROGRESS" || status == "CREATE_IN_PROGRESS") {
+                break;
+            }
+            if !is_stack && status.ends_with("_FAILED") {
+                // Keep going, the earliest failure is the one that caused the rest
+                failure = Some(StackFailure {
+                    resource: resource.to_string(),
+                    reason: event
+                        .resource_status_reason()
+                        .unwrap_or("No reason was given")
+                        .to_string(),
+                });
             }
-        }))
+        }
+
+        info!(?failure, "found stack failure");
+        Ok(failure)
+    }
+
+    /// The `ClientRequestToken` of the operation that last put the stack in
+    /// `stack_status`, from the stack's own events.
+    ///
+    /// Every event of an operation carries its token, including those of its
+    /// rollback. Updates made outside of the bot, e.g. from the console, carry
+    /// a token of their own or none at all.
+    async fn get_request_token(
+        &self,
+        server: &ServerDefinition,
+        stack_status: &str,
+    ) -> Result<Option<String>> {
+        let response = self
+            .client
+            .describe_stack_events()
+            .stack_name(&server.stack_name)
+            .send()
+            .await?;
+
+        let request_token = response
+            .stack_events()
+            .iter()
+            .find(|event| {
+                event.logical_resource_id() == Some(server.stack_name.as_str())
+                    && event.resource_status().map(|status| status.as_str()) == Some(stack_status)
+            })
+            .and_then(|event| event.client_request_token())
+            .map(|token| token.to_string());
+
+        info!(?request_token, stack_status, "found stack request token");
+        Ok(request_token)
     }
 }
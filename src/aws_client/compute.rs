@@ -1,9 +1,13 @@
-use anyhow::Result;
 use aws_sdk_autoscaling::types::Instance;
-use serde_json::{json, Value};
-use tracing::info;
+
+use aws_sdk_ec2::primitives::DateTime;
+use time::OffsetDateTime;
 
 use super::ServerInfo;
+use crate::error::{Error, Result};
+use crate::model::domain::{
+    InstancePlacement, InstanceStatus, ServerDefinition, ServiceStatus, SpotPrice,
+};
 
 pub struct ServerAccessor {
     asg_client: aws_sdk_autoscaling::Client,
@@ -12,16 +16,19 @@ pub struct ServerAccessor {
 }
 
 impl ServerAccessor {
-    pub fn new(config: &aws_config::SdkConfig) -> Self {
+    pub fn new(sdk_config: &aws_config::SdkConfig) -> Self {
         ServerAccessor {
-            asg_client: aws_sdk_autoscaling::Client::new(config),
-            ec2_client: aws_sdk_ec2::Client::new(config),
-            ecs_client: aws_sdk_ecs::Client::new(config),
+            asg_client: aws_sdk_autoscaling::Client::new(sdk_config),
+            ec2_client: aws_sdk_ec2::Client::new(sdk_config),
+            ecs_client: aws_sdk_ecs::Client::new(sdk_config),
         }
     }
 
-    /// Given an EC2 instance ID, return it's public IPv4 address
-    async fn get_instance_ip(&self, instance_id: &str) -> Result<String> {
+    /// Given an EC2 instance ID, return its public IPv4 address and launch time, if known.
+    async fn describe_instance(
+        &self,
+        instance_id: &str,
+    ) -> Result<(Option<String>, Option<OffsetDateTime>)> {
         let response = self
             .ec2_client
             .describe_instances()
@@ -29,39 +36,79 @@ impl ServerAccessor {
             .send()
             .await?;
 
-        let ip_address = response
+        let instance = response
             .reservations()
             .first()
-            .and_then(|res| res.instances().first())
-            .and_then(|instance| instance.public_ip_address());
+            .and_then(|res| res.instances().first());
+
+        let ip = instance
+            .and_then(|instance| instance.public_ip_address())
+            .map(|ip| ip.to_string());
+        let launched_at = instance
+            .and_then(|instance| instance.launch_time())
+            .map(|launch_time| OffsetDateTime::from_unix_timestamp(launch_time.secs()))
+            .transpose()?;
 
-        Ok(ip_address.expect("No IP address found").to_string())
+        Ok((ip, launched_at))
     }
 
-    /// Return true if the factorio service has at least one actively runing, false otherwise.
-    async fn is_ecs_running(&self) -> Result<bool> {
-        let response = self
-            .ecs_client
-            .describe_services()
-            .cluster("factorio-ecs-spot-cluster")
-            .services("factorio-ecs-spot-ecs-service")
-            .send()
-            .await?;
+    /// The type and availability zone of the server's current instance, if any.
+    pub async fn get_instance_placement(
+        &self,
+        server: &ServerDefinition,
+    ) -> Result<Option<InstancePlacement>> {
+        Ok(self.get_asg_instance(server).await?.and_then(|instance| {
+            Some(InstancePlacement {
+                instance_type: instance.instance_type()?.to_string(),
+                availability_zone: instance.availability_zone()?.to_string(),
+            })
+        }))
+    }
 
-        let running_tasks = response
-            .services()
-            .first()
-            .and_then(|service| service.deployments().first())
-            .map(|deployment| deployment.running_count);
+    /// Linux spot prices for the placement between `from` and `to`, oldest first.
+    /// The price already in effect at `from` is included.
+    pub async fn get_spot_price_history(
+        &self,
+        placement: &InstancePlacement,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+    ) -> Result<Vec<SpotPrice>> {
+        let mut prices = vec![];
+        let mut pages = self
+            .ec2_client
+            .describe_spot_price_history()
+            .instance_types(placement.instance_type.as_str().into())
+            .availability_zone(&placement.availability_zone)
+            .product_descriptions("Linux/UNIX")
+            .start_time(DateTime::from_secs(from.unix_timestamp()))
+            .end_time(DateTime::from_secs(to.unix_timestamp()))
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            for entry in page?.spot_price_history() {
+                let (Some(timestamp), Some(price)) = (entry.timestamp(), entry.spot_price()) else {
+                    continue;
+                };
+                prices.push(SpotPrice {
+                    timestamp: OffsetDateTime::from_unix_timestamp(timestamp.secs())?,
+                    price: price
+                        .parse()
+                        .map_err(|_| Error::Data(format!("Invalid spot price {}", price)))?,
+                });
+            }
+        }
 
-        Ok(running_tasks.expect("Deployment was not found") > 0)
+        prices.sort_by_key(|price| price.timestamp);
+        Ok(prices)
     }
 
-    async fn get_asg_instance(&self) -> Result<Option<Instance>> {
+    /// The instance currently in the server's ASG, if any.
+    pub async fn get_asg_instance(&self, server: &ServerDefinition) -> Result<Option<Instance>> {
         let _asg_response = self
             .asg_client
             .describe_auto_scaling_groups()
-            .auto_scaling_group_names("factorio-ecs-spot-asg")
+            .auto_scaling_group_names(&server.asg_name)
             .send()
             .await?;
 
@@ -74,58 +121,73 @@ impl ServerAccessor {
 }
 
 impl ServerInfo for ServerAccessor {
-    async fn get_server_ip_response(&self) -> Result<Value> {
-        let asg_instance = self.get_asg_instance().await?;
-
-        let content = if let Some(asg_instance) = asg_instance {
-            match asg_instance.lifecycle_state().unwrap() {
-                aws_sdk_autoscaling::types::LifecycleState::InService => {
-                    info!("ASG instance is InService");
-                    let ip_future = self.get_instance_ip(asg_instance.instance_id().unwrap());
-                    let is_ecs_running_future = self.is_ecs_running();
-
-                    let ip = ip_future.await?;
-                    if is_ecs_running_future.await? {
-                        format!("Server is up and running at IP: `{}`!", ip)
-                    } else {
-                        format!(
-                            "Server IP will be: `{}`. However, factorio has not started running yet.",
-                            ip
-                        )
-                    }
-                }
-                _not_running_state => {
-                    format!("Server instance is in the {:#?} state", _not_running_state)
-                }
-            }
-        } else {
-            "No server is running.".to_string()
+    async fn get_instance_status(
+        &self,
+        server: &ServerDefinition,
+    ) -> Result<Option<InstanceStatus>> {
+        let Some(asg_instance) = self.get_asg_instance(server).await? else {
+            return Ok(None);
         };
-
-        Ok(json!({
-                "type": 4,
-                "data": {
-                    "tts": false,
-                    "content": content,
-                    "embeds": [],
-                    "allowed_mentions": { "parse": [] }
-                }
+        let instance_id = asg_instance.instance_id().unwrap_or_default().to_string();
+        let (ip, launched_at) = self.describe_instance(&instance_id).await?;
+
+        Ok(Some(InstanceStatus {
+            instance_id,
+            lifecycle: asg_instance
+                .lifecycle_state()
+                .map(|state| state.as_str().to_string())
+                .unwrap_or_default(),
+            ip,
+            launched_at,
         }))
     }
 
-    async fn get_running_server_ip(&self) -> Result<Option<String>> {
-        let asg_instance = self.get_asg_instance().await?;
+    async fn get_service_status(&self, server: &ServerDefinition) -> Result<Option<ServiceStatus>> {
+        let response = self
+            .ecs_client
+            .describe_services()
+            .cluster(&server.cluster_name)
+            .services(&server.service_name)
+            .send()
+            .await?;
 
-        if let Some(asg_instance) = asg_instance {
-            Ok(match asg_instance.lifecycle_state().unwrap() {
-                aws_sdk_autoscaling::types::LifecycleState::InService => Some(
-                    self.get_instance_ip(asg_instance.instance_id().unwrap())
-                        .await?,
-                ),
-                _ => None,
-            })
-        } else {
-            Ok(None)
-        }
+        let Some(service) = response.services().first() else {
+            return Ok(None);
+        };
+
+        let tasks = self
+            .ecs_client
+            .list_tasks()
+            .cluster(&server.cluster_name)
+            .service_name(&server.service_name)
+            .send()
+            .await?;
+
+        let task = match tasks.task_arns().first() {
+            Some(task_arn) => self
+                .ecs_client
+                .describe_tasks()
+                .cluster(&server.cluster_name)
+                .tasks(task_arn)
+                .send()
+                .await?
+                .tasks()
+                .first()
+                .and_then(|task| task.last_status())
+                .map(|status| status.to_string()),
+            None => None,
+        };
+
+        Ok(Some(ServiceStatus {
+            rollout: service
+                .deployments()
+                .first()
+                .and_then(|deployment| deployment.rollout_state())
+                .map(|state| state.as_str().to_string()),
+            desired: service.desired_count,
+            running: service.running_count,
+            pending: service.pending_count,
+            task,
+        }))
     }
 }
@@ -1,21 +1,300 @@
-use anyhow::Result;
-use serde_dynamo::{from_items, to_attribute_value, to_item};
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::{AttributeValue, DeleteRequest, WriteRequest};
+use serde_dynamo::{from_item, from_items, to_attribute_value, to_item};
+use time::OffsetDateTime;
 use tracing::info;
 
+use crate::config::Config;
+use crate::error::{Error, Result};
 use crate::model::{
-    domain::StartServerInteraction,
-    dynamo::{Command, DiscordInteraction},
+    domain::{Budget, ServerDefinition, Session, StartServerInteraction, StopServerInteraction},
+    dynamo::{
+        BudgetRecord, Command, DiscordInteraction, IdleServer, ServerRecord, SessionRecord,
+        SpotInterruption,
+    },
 };
 
+/// Most items a single `BatchWriteItem` request may hold.
+const MAX_BATCH_WRITE: usize = 25;
+/// Attempts at writing a batch before its unprocessed items are given up on.
+const MAX_BATCH_ATTEMPTS: u32 = 5;
+/// Wait before retrying unprocessed items, doubled on every attempt.
+const BATCH_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// The key of an interaction in the interactions table, see [`DiscordInteraction::key`].
+fn interaction_key(
+    (command, request_token): (Command, &str),
+) -> Result<HashMap<String, AttributeValue>> {
+    Ok(HashMap::from([
+        ("command".to_string(), to_attribute_value(command)?),
+        (
+            "request_token".to_string(),
+            to_attribute_value(request_token)?,
+        ),
+    ]))
+}
+
 pub struct DynamoDBAccessor {
     client: aws_sdk_dynamodb::Client,
+    servers_table: String,
+    interactions_table: String,
+    idle_table: String,
+    sessions_table: String,
+    interruptions_table: String,
+    budget_table: String,
+    fallback_server: ServerDefinition,
 }
 
 impl DynamoDBAccessor {
-    pub fn new(config: &aws_config::SdkConfig) -> Self {
+    pub fn new(sdk_config: &aws_config::SdkConfig, config: &Config) -> Self {
         DynamoDBAccessor {
-            client: aws_sdk_dynamodb::Client::new(config),
+            client: aws_sdk_dynamodb::Client::new(sdk_config),
+            servers_table: config.servers_table.clone(),
+            interactions_table: config.interactions_table.clone(),
+            idle_table: config.idle_table.clone(),
+            sessions_table: config.sessions_table.clone(),
+            interruptions_table: config.interruptions_table.clone(),
+            budget_table: config.budget_table.clone(),
+            fallback_server: config.fallback_server.clone(),
+        }
+    }
+
+    /// Looks up a server by name in the server registry. The default server
+    /// falls back to its configured resources when it is not registered.
+    pub async fn get_server(&self, name: &str) -> Result<Option<ServerDefinition>> {
+        let response = self
+            .client
+            .get_item()
+            .table_name(&self.servers_table)
+            .key("name", to_attribute_value(name)?)
+            .send()
+            .await?;
+
+        Ok(match response.item {
+            Some(item) => Some(from_item::<_, ServerRecord>(item)?.into()),
+            None if name == self.fallback_server.name => Some(self.fallback_server.clone()),
+            None => None,
+        })
+    }
+
+    /// Returns every server in the registry, and the default server if it is
+    /// only configured.
+    pub async fn list_servers(&self) -> Result<Vec<ServerDefinition>> {
+        let mut servers = vec![];
+        let mut pages = self
+            .client
+            .scan()
+            .table_name(&self.servers_table)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let records: Vec<ServerRecord> = from_items(page?.items().to_vec())?;
+            servers.extend(records.into_iter().map(ServerDefinition::from));
+        }
+        if !servers
+            .iter()
+            .any(|server| server.name == self.fallback_server.name)
+        {
+            servers.push(self.fallback_server.clone());
+        }
+        Ok(servers)
+    }
+
+    /// When the server was first seen without players, if it is currently idle.
+    pub async fn get_idle_since(&self, server: &str) -> Result<Option<OffsetDateTime>> {
+        let response = self
+            .client
+            .get_item()
+            .table_name(&self.idle_table)
+            .key("server", to_attribute_value(server)?)
+            .send()
+            .await?;
+
+        Ok(match response.item {
+            Some(item) => {
+                let record: IdleServer = from_item(item)?;
+                Some(OffsetDateTime::from_unix_timestamp(record.idle_since)?)
+            }
+            None => None,
+        })
+    }
+
+    pub async fn set_idle_since(&self, server: &str, idle_since: OffsetDateTime) -> Result<()> {
+        let item = to_item(IdleServer {
+            server: server.to_string(),
+            idle_since: idle_since.unix_timestamp(),
+        })?;
+
+        self.client
+            .put_item()
+            .table_name(&self.idle_table)
+            .set_item(Some(item))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn clear_idle(&self, server: &str) -> Result<()> {
+        self.client
+            .delete_item()
+            .table_name(&self.idle_table)
+            .key("server", to_attribute_value(server)?)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Creates or overwrites a session.
+    pub async fn put_session(&self, session: &Session) -> Result<()> {
+        let item = to_item(SessionRecord::from(session))?;
+        info!(?item, "Saving session");
+
+        self.client
+            .put_item()
+            .table_name(&self.sessions_table)
+            .set_item(Some(item))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// The server's latest session, if it has not stopped yet.
+    pub async fn get_open_session(&self, server: &str) -> Result<Option<Session>> {
+        Ok(self
+            .list_sessions(server, 1)
+            .await?
+            .into_iter()
+            .next()
+            .filter(|session| session.stopped_at.is_none()))
+    }
+
+    /// The server's latest `count` sessions, newest first.
+    pub async fn list_sessions(&self, server: &str, count: usize) -> Result<Vec<Session>> {
+        let mut sessions = vec![];
+        let mut pages = self
+            .client
+            .query()
+            .table_name(&self.sessions_table)
+            .key_condition_expression("#server = :server")
+            .expression_attribute_names("#server", "server")
+            .expression_attribute_values(":server", to_attribute_value(server)?)
+            .scan_index_forward(false)
+            .limit(count.min(100) as i32)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let records: Vec<SessionRecord> = from_items(page?.items().to_vec())?;
+            for record in records {
+                sessions.push(Session::try_from(record)?);
+            }
+            if sessions.len() >= count {
+                break;
+            }
+        }
+        sessions.truncate(count);
+        Ok(sessions)
+    }
+
+    /// The server's last session started before `before`. A server runs one
+    /// session at a time, so no earlier one can last past `before`.
+    pub async fn get_session_before(
+        &self,
+        server: &str,
+        before: OffsetDateTime,
+    ) -> Result<Option<Session>> {
+        let response = self
+            .client
+            .query()
+            .table_name(&self.sessions_table)
+            .key_condition_expression("#server = :server AND started_at < :before")
+            .expression_attribute_names("#server", "server")
+            .expression_attribute_values(":server", to_attribute_value(server)?)
+            .expression_attribute_values(":before", to_attribute_value(before.unix_timestamp())?)
+            .scan_index_forward(false)
+            .limit(1)
+            .send()
+            .await?;
+
+        let records: Vec<SessionRecord> = from_items(response.items().to_vec())?;
+        Ok(records
+            .into_iter()
+            .next()
+            .map(Session::try_from)
+            .transpose()?)
+    }
+
+    /// The server's sessions started since `since`, newest first.
+    pub async fn list_sessions_since(
+        &self,
+        server: &str,
+        since: OffsetDateTime,
+    ) -> Result<Vec<Session>> {
+        let mut sessions = vec![];
+        let mut pages = self
+            .client
+            .query()
+            .table_name(&self.sessions_table)
+            .key_condition_expression("#server = :server AND started_at >= :since")
+            .expression_attribute_names("#server", "server")
+            .expression_attribute_values(":server", to_attribute_value(server)?)
+            .expression_attribute_values(":since", to_attribute_value(since.unix_timestamp())?)
+            .scan_index_forward(false)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let records: Vec<SessionRecord> = from_items(page?.items().to_vec())?;
+            for record in records {
+                sessions.push(Session::try_from(record)?);
+            }
         }
+        Ok(sessions)
+    }
+
+    /// The monthly budget, if one was set.
+    pub async fn get_budget(&self) -> Result<Option<Budget>> {
+        let response = self
+            .client
+            .get_item()
+            .table_name(&self.budget_table)
+            .key("name", to_attribute_value(BudgetRecord::NAME)?)
+            .send()
+            .await?;
+
+        Ok(match response.item {
+            Some(item) => Some(from_item::<_, BudgetRecord>(item)?.into()),
+            None => None,
+        })
+    }
+
+    pub async fn put_budget(&self, budget: &Budget) -> Result<()> {
+        let item = to_item(BudgetRecord::from(budget))?;
+        info!(?item, "Saving budget");
+
+        self.client
+            .put_item()
+            .table_name(&self.budget_table)
+            .set_item(Some(item))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Records that the server's spot instance is being reclaimed.
+    pub async fn record_interruption(&self, interruption: SpotInterruption) -> Result<()> {
+        let item = to_item(interruption)?;
+        info!(?item, "Recording spot interruption");
+
+        self.client
+            .put_item()
+            .table_name(&self.interruptions_table)
+            .set_item(Some(item))
+            .send()
+            .await?;
+        Ok(())
     }
 
     pub async fn save_interaction<T: Into<DiscordInteraction>>(&self, item: T) -> Result<()> {
@@ -24,7 +303,7 @@ impl DynamoDBAccessor {
 
         self.client
             .put_item()
-            .table_name("discord-interaction-tokens")
+            .table_name(&self.interactions_table)
             .set_item(Some(item))
             .send()
             .await?;
@@ -36,33 +315,130 @@ impl DynamoDBAccessor {
 
         self.client
             .delete_item()
-            .table_name("discord-interaction-tokens")
-            .key("command", to_attribute_value(&item.command)?)
-            .key("timestamp", to_attribute_value(&item.timestamp)?)
+            .table_name(&self.interactions_table)
+            .set_key(Some(interaction_key(item.key())?))
             .send()
             .await?;
         Ok(())
     }
 
-    pub async fn get_latest_start(&self) -> Result<Option<StartServerInteraction>> {
-        let response = self
+    /// Starts of the server that are still waiting on a stack update, including
+    /// those made while another update was in progress. Expired ones are left
+    /// out, as DynamoDB only removes them some time after their TTL.
+    pub async fn list_pending_starts(
+        &self,
+        server: &str,
+        now: OffsetDateTime,
+    ) -> Result<Vec<StartServerInteraction>> {
+        let mut starts = vec![];
+        let mut pages = self
             .client
             .query()
-            .table_name("discord-interaction-tokens")
+            .table_name(&self.interactions_table)
             .key_condition_expression("command = :command")
+            .filter_expression("#server = :server AND #ttl > :now")
+            .expression_attribute_names("#server", "server")
+            .expression_attribute_names("#ttl", "ttl")
             .expression_attribute_values(":command", to_attribute_value(Command::FactorioStart)?)
-            // reverse order with limit of one to get the latest timestamp
-            .limit(1)
-            .scan_index_forward(false)
-            .send()
-            .await?;
+            .expression_attribute_values(":server", to_attribute_value(server)?)
+            .expression_attribute_values(":now", to_attribute_value(now.unix_timestamp())?)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let items: Vec<DiscordInteraction> = from_items(page?.items().to_vec())?;
+            for item in items {
+                starts.push(item.try_into()?);
+            }
+        }
+        Ok(starts)
+    }
+
+    /// Deletes interactions in batches, retrying those DynamoDB did not process
+    /// a few times before failing with the keys that are left.
+    pub async fn delete_interactions<T: Into<DiscordInteraction>>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+    ) -> Result<()> {
+        let mut requests = vec![];
+        for item in items {
+            let item: DiscordInteraction = item.into();
+            let delete = DeleteRequest::builder()
+                .set_key(Some(interaction_key(item.key())?))
+                .build()
+                .map_err(|err| Error::Data(err.to_string()))?;
+            requests.push(WriteRequest::builder().delete_request(delete).build());
+        }
 
-        let items: Vec<DiscordInteraction> = from_items(response.items().to_vec())?;
+        for batch in requests.chunks(MAX_BATCH_WRITE) {
+            let mut pending = HashMap::from([(self.interactions_table.clone(), batch.to_vec())]);
+            let mut delay = BATCH_RETRY_DELAY;
+            for attempt in 1..=MAX_BATCH_ATTEMPTS {
+                let response = self
+                    .client
+                    .batch_write_item()
+                    .set_request_items(Some(pending))
+                    .send()
+                    .await?;
+                pending = response.unprocessed_items.unwrap_or_default();
+                pending.retain(|_, requests| !requests.is_empty());
+                if pending.is_empty() || attempt == MAX_BATCH_ATTEMPTS {
+                    break;
+                }
+                // Unprocessed items are usually throttled, give the table a moment
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
 
-        if items.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(items.into_iter().nth(0).unwrap().try_into()?))
+            if !pending.is_empty() {
+                let left: Vec<_> = pending
+                    .values()
+                    .flatten()
+                    .filter_map(|request| request.delete_request())
+                    .filter_map(|delete| delete.key().get("request_token"))
+                    .filter_map(|request_token| request_token.as_s().ok())
+                    .map(String::as_str)
+                    .collect();
+                return Err(Error::Throttled(format!(
+                    "could not delete the interactions {}",
+                    left.join(", ")
+                )));
+            }
         }
+        Ok(())
+    }
+
+    /// The start that made the stack update with this `ClientRequestToken`.
+    pub async fn get_start(&self, request_token: &str) -> Result<Option<StartServerInteraction>> {
+        Ok(self
+            .get_interaction(Command::FactorioStart, request_token)
+            .await?
+            .map(|item| item.try_into())
+            .transpose()?)
+    }
+
+    /// The stop that made the stack update with this `ClientRequestToken`.
+    pub async fn get_stop(&self, request_token: &str) -> Result<Option<StopServerInteraction>> {
+        Ok(self
+            .get_interaction(Command::FactorioStop, request_token)
+            .await?
+            .map(|item| item.try_into())
+            .transpose()?)
+    }
+
+    async fn get_interaction(
+        &self,
+        command: Command,
+        request_token: &str,
+    ) -> Result<Option<DiscordInteraction>> {
+        let response = self
+            .client
+            .get_item()
+            .table_name(&self.interactions_table)
+            .set_key(Some(interaction_key((command, request_token))?))
+            .send()
+            .await?;
+
+        Ok(response.item.map(from_item).transpose()?)
     }
 }
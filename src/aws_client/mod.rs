@@ -1,16 +1,67 @@
-use anyhow::Result;
-use serde_json::Value;
+use crate::error::Result;
+use crate::model::domain::{
+    InstanceSize, InstanceStatus, ServerDefinition, ServerStatus, ServiceStatus, StackFailure,
+    StackStatus, UpdateOutcome,
+};
 
 pub mod cfn;
 pub mod compute;
 pub mod ddb;
+pub mod saves;
+pub mod worker;
 
 pub trait ServerUpdater {
-    async fn start_server(&self, mount_dir: &str) -> Result<Value>;
-    async fn stop_server(&self) -> Result<Value>;
+    async fn start_server(
+        &self,
+        server: &ServerDefinition,
+        mount_dir: &str,
+        size: Option<&InstanceSize>,
+        request_token: &str,
+    ) -> Result<UpdateOutcome>;
+    async fn stop_server(
+        &self,
+        server: &ServerDefinition,
+        request_token: Option<&str>,
+    ) -> Result<UpdateOutcome>;
 }
 
 pub trait ServerInfo {
-    async fn get_server_ip_response(&self) -> Result<Value>;
-    async fn get_running_server_ip(&self) -> Result<Option<String>>;
+    async fn get_instance_status(
+        &self,
+        server: &ServerDefinition,
+    ) -> Result<Option<InstanceStatus>>;
+    async fn get_service_status(&self, server: &ServerDefinition) -> Result<Option<ServiceStatus>>;
+}
+
+pub trait StackInfo {
+    async fn get_stack_status(&self, server: &ServerDefinition) -> Result<StackStatus>;
+    async fn get_last_failure(&self, server: &ServerDefinition) -> Result<Option<StackFailure>>;
+    async fn get_request_token(
+        &self,
+        server: &ServerDefinition,
+        stack_status: &str,
+    ) -> Result<Option<String>>;
+}
+
+pub trait SaveCatalog {
+    async fn list_saves(&self, server: &ServerDefinition) -> Result<Vec<String>>;
+    async fn save_exists(&self, server: &ServerDefinition, save: &str) -> Result<bool>;
+}
+
+/// Takes a snapshot of the server's stack, instance and service together.
+pub async fn get_server_status(
+    stack_info: &impl StackInfo,
+    server_info: &impl ServerInfo,
+    server: &ServerDefinition,
+) -> Result<ServerStatus> {
+    let (stack, instance, service) = tokio::try_join!(
+        stack_info.get_stack_status(server),
+        server_info.get_instance_status(server),
+        server_info.get_service_status(server),
+    )?;
+    Ok(ServerStatus {
+        stack,
+        instance,
+        service,
+    })
 }
@@ -0,0 +1,67 @@
+use tracing::info;
+
+use super::SaveCatalog;
+use crate::config::{Config, ConfigError};
+use crate::error::Result;
+use crate::model::domain::ServerDefinition;
+
+/// Lists the factorio saves stored as directories under a server's S3 prefix,
+/// e.g. `s3://bucket/saves/modded/my-save/`.
+pub struct SaveAccessor {
+    client: aws_sdk_s3::Client,
+    bucket: Option<String>,
+    prefix: String,
+}
+
+impl SaveAccessor {
+    pub fn new(sdk_config: &aws_config::SdkConfig, config: &Config) -> Self {
+        SaveAccessor {
+            client: aws_sdk_s3::Client::new(sdk_config),
+            bucket: config.saves_bucket.clone(),
+            prefix: config.saves_prefix.clone(),
+        }
+    }
+}
+
+impl SaveCatalog for SaveAccessor {
+    async fn list_saves(&self, server: &ServerDefinition) -> Result<Vec<String>> {
+        let bucket = self
+            .bucket
+            .as_ref()
+            .ok_or(ConfigError::Missing("FACTORIO_SAVES_BUCKET"))?;
+        let prefix = format!("{}{}/", self.prefix, server.name);
+
+        let mut saves = vec![];
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(&prefix)
+            .delimiter("/")
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            saves.extend(
+                page?
+                    .common_prefixes()
+                    .iter()
+                    .filter_map(|common| common.prefix())
+                    .filter_map(|save| save.strip_prefix(&prefix))
+                    .map(|save| save.trim_end_matches('/').to_string())
+                    .filter(|save| !save.is_empty()),
+            );
+        }
+
+        info!(?saves, server.name, "listed saves");
+        Ok(saves)
+    }
+
+    async fn save_exists(&self, server: &ServerDefinition, save: &str) -> Result<bool> {
+        Ok(self
+            .list_saves(server)
+            .await?
+            .iter()
+            .any(|name| name == save))
+    }
+}
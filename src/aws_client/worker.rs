@@ -0,0 +1,35 @@
+use aws_sdk_lambda::{primitives::Blob, types::InvocationType};
+use tracing::info;
+
+use crate::error::Result;
+use crate::{config::Config, discord::model::Interaction};
+
+/// Hands interactions off to the worker Lambda, so the command Lambda can
+/// answer Discord before any slow AWS calls are made.
+pub struct WorkerInvoker {
+    client: aws_sdk_lambda::Client,
+    function_name: String,
+}
+
+impl WorkerInvoker {
+    pub fn new(sdk_config: &aws_config::SdkConfig, config: &Config) -> Self {
+        WorkerInvoker {
+            client: aws_sdk_lambda::Client::new(sdk_config),
+            function_name: config.worker_function_name.clone(),
+        }
+    }
+
+    /// Asynchronously invokes the worker with the interaction as its payload.
+    pub async fn dispatch(&self, interaction: &Interaction) -> Result<()> {
+        info!(interaction.id, "dispatching interaction to worker");
+
+        self.client
+            .invoke()
+            .function_name(&self.function_name)
+            .invocation_type(InvocationType::Event)
+            .payload(Blob::new(serde_json::to_vec(interaction)?))
+            .send()
+            .await?;
+        Ok(())
+    }
+}
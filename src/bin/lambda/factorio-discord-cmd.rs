@@ -1,16 +1,18 @@
 use factorio_server_lambda::{
-    aws_client::{
-        cfn::CfnAccessor, compute::ServerAccessor, ddb::DynamoDBAccessor, ServerInfo, ServerUpdater,
-    },
-    model::domain::StartServerInteraction,
+    aws_client::worker::WorkerInvoker,
+    commands::{CommandRegistry, Context},
+    config::Config,
+    error::Error as CrateError,
 };
-use lambda_http::{run, service_fn, Body, Error, Request, Response};
-use serde_json::Value;
-use time::OffsetDateTime;
-use tracing::{info, warn};
+use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, Response};
+use tracing::{error, info, warn};
 
 use factorio_server_lambda::discord::{
-    auth::DiscordAuthenticator, SignedRequest, VerifyDiscordReq,
+    auth::DiscordAuthenticator,
+    model::{Interaction, InteractionType},
+    present,
+    response::InteractionResponse,
+    SignedRequest, VerifyDiscordReq,
 };
 
 /// This is the main body for the function.
@@ -19,19 +21,27 @@ use factorio_server_lambda::discord::{
 /// - https://github.com/awslabs/aws-lambda-rust-runtime/tree/main/examples
 async fn function_handler(
     discord_auth: &DiscordAuthenticator,
-    server_accessor: &ServerAccessor,
-    cfn_accessor: &CfnAccessor,
-    ddb: &DynamoDBAccessor,
+    registry: &CommandRegistry,
+    ctx: &Context,
+    worker: &WorkerInvoker,
     request: Request,
 ) -> Result<Response<Body>, Error> {
     // Extract some useful information from the request
     info!("recieved request");
 
     let body = match request.body() {
-        Body::Empty => todo!(),
-        Body::Text(text) => text,
-        Body::Binary(_) => todo!(),
+        Body::Empty => return bad_request("Missing body"),
+        Body::Text(text) => text.as_str(),
+        Body::Binary(bytes) => match std::str::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(_) => return bad_request("Body is not valid UTF-8"),
+        },
     };
+    // The request ID is in every log line of this invocation
+    let error_id = request
+        .lambda_context_ref()
+        .map(|context| context.request_id.clone())
+        .unwrap_or_default();
 
     if let Some(auth_err) = discord_auth
         .verify(SignedRequest {
@@ -50,53 +60,112 @@ async fn function_handler(
         return Ok(resp);
     };
 
-    let parsed_body: Value = serde_json::from_str(body.as_str())?;
-    info!(%parsed_body);
-
-    let msg_type = parsed_body["type"].as_i64().expect("No type found");
+    let interaction: Interaction = match serde_json::from_str(body) {
+        Ok(interaction) => interaction,
+        Err(parse_err) => {
+            warn!(?parse_err, "could not parse interaction");
+            return bad_request("Malformed interaction");
+        }
+    };
+    info!(?interaction);
 
-    if msg_type == 1 {
+    if interaction.kind == InteractionType::Ping {
         info!("ping event");
         let resp = Response::builder()
             .status(200)
             .header("content-type", "application/json")
-            .body("{\"type\": 1}".into())
+            .body(serde_json::to_string(&InteractionResponse::pong())?.into())
             .map_err(Box::new)?;
 
         return Ok(resp);
     }
 
-    let interaction = StartServerInteraction {
-        token: parsed_body["token"]
-            .as_str()
-            .expect("Missing interaction token")
-            .to_string(),
-        timestamp: OffsetDateTime::now_utc(),
+    let Some(subcommand) = interaction.data.as_ref().and_then(|data| data.subcommand()) else {
+        warn!(?interaction.kind, "interaction without a subcommand");
+        return bad_request("Missing subcommand");
     };
 
-    let response = match parsed_body["data"]["options"][0]["name"]
-        .as_str()
-        .expect("missing command")
-    {
-        "start" => {
-            let mount_dir = parsed_body["data"]["options"][0]["options"][0]["value"]
-                .as_str()
-                .expect("missing mount dir");
-            let response = cfn_accessor.start_server(mount_dir).await?;
-            ddb.save_interaction(interaction).await?;
-            response
+    match interaction.kind {
+        InteractionType::ApplicationCommand => {}
+        InteractionType::ApplicationCommandAutocomplete => {
+            // Autocomplete can not show errors, so offer nothing instead
+            let choices = match registry.autocomplete(ctx, &interaction).await {
+                Ok(choices) => choices,
+                Err(autocomplete_err) => {
+                    error!(?autocomplete_err, error_id, "autocomplete failed");
+                    vec![]
+                }
+            };
+            let response = InteractionResponse::autocomplete(choices);
+            let resp = Response::builder()
+                .status(200)
+                .header("content-type", "application/json")
+                .body(serde_json::to_string(&response)?.into())
+                .map_err(Box::new)?;
+            return Ok(resp);
         }
-        "stop" => cfn_accessor.stop_server().await?,
-        "ip" => server_accessor.get_server_ip_response().await?,
-        _ => panic!("Unknown command"),
-    };
+        unsupported => {
+            warn!(?unsupported, "unsupported interaction type");
+            return bad_request("Unsupported interaction type");
+        }
+    }
+
+    if let Err(reason) = registry.validate(subcommand) {
+        warn!(reason, "invalid command");
+        // Tells the user why, which a bare 400 would not
+        let response =
+            InteractionResponse::message(present::error(&CrateError::BadInput(reason), &error_id));
+        let resp = Response::builder()
+            .status(200)
+            .header("content-type", "application/json")
+            .body(serde_json::to_string(&response)?.into())
+            .map_err(Box::new)?;
+        return Ok(resp);
+    }
+
+    if let Some(response) = ctx
+        .config
+        .command_policies
+        .refusal(&subcommand.name, &interaction)
+    {
+        warn!(subcommand.name, invoker = ?interaction.invoker(), "unauthorized command");
+        let resp = Response::builder()
+            .status(200)
+            .header("content-type", "application/json")
+            .body(serde_json::to_string(&response)?.into())
+            .map_err(Box::new)?;
+        return Ok(resp);
+    }
+
+    // Discord only waits 3 seconds for a reply, so the actual work is done by
+    // the worker, which edits the deferred message once it is finished.
+    if let Err(dispatch_err) = worker.dispatch(&interaction).await {
+        error!(?dispatch_err, error_id, "could not dispatch to the worker");
+        let response = InteractionResponse::message(present::error(&dispatch_err, &error_id));
+        let resp = Response::builder()
+            .status(200)
+            .header("content-type", "application/json")
+            .body(serde_json::to_string(&response)?.into())
+            .map_err(Box::new)?;
+        return Ok(resp);
+    }
 
     // Return something that implements IntoResponse.
     // It will be serialized to the right response event automatically by the runtime
     let resp = Response::builder()
         .status(200)
         .header("content-type", "application/json")
-        .body(response.to_string().into())
+        .body(serde_json::to_string(&InteractionResponse::deferred())?.into())
+        .map_err(Box::new)?;
+    Ok(resp)
+}
+
+/// Rejects an interaction that could not be understood.
+fn bad_request(reason: &str) -> Result<Response<Body>, Error> {
+    let resp = Response::builder()
+        .status(400)
+        .header("content-type", "text/html")
+        .body(reason.into())
         .map_err(Box::new)?;
     Ok(resp)
 }
@@ -113,15 +182,16 @@ async fn main() -> Result<(), Error> {
         .without_time()
         .init();
 
-    let discord_auth = DiscordAuthenticator::new();
-
     let aws_config = aws_config::load_from_env().await;
-    let service_accessor = ServerAccessor::new(&aws_config);
-    let cfn_accessor = CfnAccessor::new(&aws_config);
-    let ddb = DynamoDBAccessor::new(&aws_config);
+    let config = Config::load(&aws_config).await?;
+
+    let discord_auth = DiscordAuthenticator::new(config.discord_public_key);
+    let worker = WorkerInvoker::new(&aws_config, &config);
+    let ctx = Context::new(&aws_config, config);
+    let registry = CommandRegistry::new();
 
     run(service_fn(|event: Request| async {
-        function_handler(&discord_auth, &service_accessor, &cfn_accessor, &ddb, event).await
+        function_handler(&discord_auth, &registry, &ctx, &worker, event).await
     }))
     .await
 }
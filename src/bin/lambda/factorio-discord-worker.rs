@@ -0,0 +1,71 @@
+use factorio_server_lambda::{
+    commands::{CommandRegistry, Context},
+    config::Config,
+    discord::{model::Interaction, present, response::MessageData},
+    error::Result,
+};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use tracing::{error, info, warn};
+
+/// Runs a command that the command Lambda has already acknowledged, then
+/// replaces the deferred "thinking" message with the result.
+async fn function_handler(
+    registry: &CommandRegistry,
+    ctx: &Context,
+    event: LambdaEvent<Interaction>,
+) -> Result<(), Error> {
+    let interaction = event.payload;
+    info!(?interaction, "Received interaction");
+
+    match registry.dispatch(ctx, &interaction).await {
+        Ok(message) => {
+            ctx.webhook
+                .edit_original(&interaction.token, &message)
+                .await?
+        }
+        Err(command_err) => {
+            // The request ID is in every log line of this invocation
+            let error_id = event.context.request_id;
+            error!(?command_err, error_id, "command failed");
+            report_error(ctx, &interaction, &present::error(&command_err, &error_id)).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the deferred message with an ephemeral error, which only the
+/// invoker sees. A deferred response can not be made ephemeral by editing it.
+async fn report_error(
+    ctx: &Context,
+    interaction: &Interaction,
+    message: &MessageData,
+) -> Result<()> {
+    if let Err(delete_err) = ctx.webhook.delete_original(&interaction.token).await {
+        warn!(?delete_err, "could not delete the deferred message");
+    }
+    ctx.webhook.followup(&interaction.token, message).await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_current_span(false)
+        // disable printing the name of the module in every log line.
+        .with_target(false)
+        // disabling time is handy because CloudWatch will add the ingestion time.
+        .without_time()
+        .init();
+
+    let aws_config = aws_config::load_from_env().await;
+    let config = Config::load(&aws_config).await?;
+
+    let ctx = Context::new(&aws_config, config);
+    let registry = CommandRegistry::new();
+
+    run(service_fn(|event: LambdaEvent<Interaction>| async {
+        function_handler(&registry, &ctx, event).await
+    }))
+    .await
+}
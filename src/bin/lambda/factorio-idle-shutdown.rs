@@ -0,0 +1,151 @@
+use aws_lambda_events::event::cloudwatch_events::CloudWatchEvent;
+use factorio_server_lambda::{
+    aws_client::{
+        cfn::CfnAccessor, compute::ServerAccessor, ddb::DynamoDBAccessor, get_server_status,
+        ServerUpdater,
+    },
+    budget,
+    config::{Config, RconSettings},
+    cost,
+    discord::{present, webhook::DiscordWebhook},
+    error::Result,
+    model::domain::{ServerDefinition, UpdateOutcome},
+    rcon::RconClient,
+};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use time::OffsetDateTime;
+use tracing::{error, info, warn};
+
+/// Runs on a schedule, shutting down servers that have had no players online
+/// for longer than the configured idle period, and warning when spending
+/// reaches a budget threshold.
+async fn function_handler(
+    config: &Config,
+    webhook: &DiscordWebhook,
+    ddb: &DynamoDBAccessor,
+    server_accessor: &ServerAccessor,
+    cfn_accessor: &CfnAccessor,
+    event: LambdaEvent<CloudWatchEvent>,
+) -> Result<(), Error> {
+    info!(?event.payload, "Received event");
+
+    match &config.rcon {
+        Some(rcon) => {
+            for server in ddb.list_servers().await? {
+                // One failing server should not keep the others running
+                if let Err(check_err) = check_server(
+                    config,
+                    rcon,
+                    webhook,
+                    ddb,
+                    server_accessor,
+                    cfn_accessor,
+                    &server,
+                )
+                .await
+                {
+                    error!(?check_err, server.name, "Idle check failed");
+                }
+            }
+        }
+        None => warn!("RCON is disabled, players can not be counted"),
+    }
+
+    // Running servers count towards the budget before they are stopped
+    Ok(budget::announce_usage(config, ddb, server_accessor, webhook).await?)
+}
+
+async fn check_server(
+    config: &Config,
+    rcon: &RconSettings,
+    webhook: &DiscordWebhook,
+    ddb: &DynamoDBAccessor,
+    server_accessor: &ServerAccessor,
+    cfn_accessor: &CfnAccessor,
+    server: &ServerDefinition,
+) -> Result<()> {
+    // Only a running server can be idle, anything else is not worth counting
+    let status = get_server_status(cfn_accessor, server_accessor, server).await?;
+    let Some(ip) = status.running_ip() else {
+        return ddb.clear_idle(&server.name).await;
+    };
+
+    let mut client = match RconClient::connect_to(ip, rcon).await {
+        Ok(client) => client,
+        Err(rcon_err) => {
+            // Factorio may still be loading, don't count it as idle.
+            warn!(?rcon_err, server.name, "Could not reach RCON");
+            return Ok(());
+        }
+    };
+
+    let players = client.players_online().await?;
+    if !players.is_empty() {
+        info!(?players, server.name, "Server is in use");
+        return ddb.clear_idle(&server.name).await;
+    }
+
+    let now = OffsetDateTime::now_utc();
+    let Some(idle_since) = ddb.get_idle_since(&server.name).await? else {
+        info!(server.name, "Server has become idle");
+        return ddb.set_idle_since(&server.name, now).await;
+    };
+
+    let idle_for = now - idle_since;
+    if idle_for < config.idle_shutdown {
+        info!(server.name, ?idle_for, "Server is idle");
+        return Ok(());
+    }
+
+    info!(server.name, ?idle_for, "Shutting down idle server");
+    // The task is torn down with the stack update, so the save has to be written first
+    client.server_save_and_wait().await?;
+    if cfn_accessor.stop_server(server, None).await? == UpdateOutcome::Updating {
+        cost::settle_session(
+            ddb,
+            server_accessor,
+            server,
+            "idle shutdown",
+            status.instance.and_then(|instance| instance.launched_at),
+        )
+        .await?;
+        webhook
+            .post_notice(&present::idle_shutdown(&server.name, idle_for))
+            .await?;
+    }
+    ddb.clear_idle(&server.name).await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_current_span(false)
+        // disable printing the name of the module in every log line.
+        .with_target(false)
+        // disabling time is handy because CloudWatch will add the ingestion time.
+        .without_time()
+        .init();
+
+    let aws_config = aws_config::load_from_env().await;
+    let config = Config::load(&aws_config).await?;
+
+    let webhook = DiscordWebhook::new(&config);
+    let ddb = DynamoDBAccessor::new(&aws_config, &config);
+    let server_accessor = ServerAccessor::new(&aws_config);
+    let cfn_accessor = CfnAccessor::new(&aws_config);
+
+    run(service_fn(|event: LambdaEvent<CloudWatchEvent>| async {
+        function_handler(
+            &config,
+            &webhook,
+            &ddb,
+            &server_accessor,
+            &cfn_accessor,
+            event,
+        )
+        .await
+    }))
+    .await
+}
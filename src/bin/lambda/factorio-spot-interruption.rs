@@ -0,0 +1,134 @@
+use aws_lambda_events::event::cloudwatch_events::CloudWatchEvent;
+use factorio_server_lambda::{
+    aws_client::{compute::ServerAccessor, ddb::DynamoDBAccessor, ServerInfo},
+    config::{Config, RconSettings},
+    cost,
+    discord::{present, webhook::DiscordWebhook},
+    error::{Error as CrateError, Result},
+    model::{domain::ServerDefinition, dynamo::SpotInterruption},
+    rcon::{RconClient, RconError},
+};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use time::OffsetDateTime;
+use tracing::{error, info, warn};
+
+/// Handles the EC2 Spot Instance Interruption Warning, sent two minutes before
+/// AWS reclaims a spot instance. Saves the game while there is still time.
+async fn function_handler(
+    config: &Config,
+    webhook: &DiscordWebhook,
+    ddb: &DynamoDBAccessor,
+    server_accessor: &ServerAccessor,
+    event: LambdaEvent<CloudWatchEvent>,
+) -> Result<(), Error> {
+    info!(?event.payload, "Received event");
+    let bad_event = |reason: &str| CrateError::BadInput(reason.to_string());
+    let detail = event
+        .payload
+        .detail
+        .ok_or_else(|| bad_event("No detail was provided"))?;
+    let instance_id = detail["instance-id"]
+        .as_str()
+        .ok_or_else(|| bad_event("No instance ID was provided"))?;
+
+    let Some(server) = find_server(ddb, server_accessor, instance_id).await? else {
+        info!(
+            instance_id,
+            "Instance does not belong to a registered server."
+        );
+        return Ok(());
+    };
+    warn!(
+        server.name,
+        instance_id, "Spot instance is being interrupted"
+    );
+
+    let instance = server_accessor.get_instance_status(&server).await?;
+    let saved = match (&instance, &config.rcon) {
+        (Some(instance), Some(rcon)) => match &instance.ip {
+            Some(ip) => match save_game(ip, rcon).await {
+                Ok(()) => true,
+                Err(rcon_err) => {
+                    error!(?rcon_err, server.name, "Could not save the game");
+                    false
+                }
+            },
+            None => false,
+        },
+        _ => false,
+    };
+
+    // The replacement instance is a new cost, so the session keeps this one's
+    if let Err(cost_err) = cost::settle_interrupted_instance(
+        ddb,
+        server_accessor,
+        &server,
+        instance.and_then(|instance| instance.launched_at),
+    )
+    .await
+    {
+        warn!(?cost_err, server.name, "Could not record the instance cost");
+    }
+
+    ddb.record_interruption(SpotInterruption {
+        server: server.name.clone(),
+        timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+        instance_id: instance_id.to_string(),
+        saved,
+    })
+    .await?;
+
+    webhook
+        .post_notice(&present::spot_interruption(&server.name, saved))
+        .await?;
+    Ok(())
+}
+
+/// Finds the server whose ASG currently holds the interrupted instance.
+async fn find_server(
+    ddb: &DynamoDBAccessor,
+    server_accessor: &ServerAccessor,
+    instance_id: &str,
+) -> Result<Option<ServerDefinition>> {
+    for server in ddb.list_servers().await? {
+        let asg_instance = server_accessor.get_asg_instance(&server).await?;
+        if asg_instance.is_some_and(|instance| instance.instance_id() == Some(instance_id)) {
+            return Ok(Some(server));
+        }
+    }
+    Ok(None)
+}
+
+async fn save_game(ip: &str, rcon: &RconSettings) -> Result<(), RconError> {
+    let mut client = RconClient::connect_to(ip, rcon).await?;
+    client
+        .broadcast("The server's instance is being reclaimed by AWS in two minutes. Saving the game, the server will restart shortly.")
+        .await?;
+    client.server_save_and_wait().await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .json()
+        .with_max_level(tracing::Level::INFO)
+        .with_current_span(false)
+        // disable printing the name of the module in every log line.
+        .with_target(false)
+        // disabling time is handy because CloudWatch will add the ingestion time.
+        .without_time()
+        .init();
+
+    let aws_config = aws_config::load_from_env().await;
+    let config = Config::load(&aws_config).await?;
+
+    let webhook = DiscordWebhook::new(&config);
+    let ddb = DynamoDBAccessor::new(&aws_config, &config);
+    let server_accessor = ServerAccessor::new(&aws_config);
+
+    run(service_fn(|event: LambdaEvent<CloudWatchEvent>| async {
+        function_handler(&config, &webhook, &ddb, &server_accessor, event).await
+    }))
+    .await
+}
@@ -1,12 +1,32 @@
-use anyhow::Result;
 use aws_lambda_events::event::cloudwatch_events::CloudWatchEvent;
-use factorio_server_lambda::aws_client::{
-    compute::ServerAccessor, ddb::DynamoDBAccessor, ServerInfo,
+use factorio_server_lambda::{
+    aws_client::{
+        cfn::CfnAccessor, compute::ServerAccessor, ddb::DynamoDBAccessor, get_server_status,
+        StackInfo,
+    },
+    config::Config,
+    discord::{
+        present, response::MessageData, webhook::DiscordWebhook, INTERACTION_TOKEN_LIFETIME,
+    },
+    error::{Error as CrateError, Result},
+    model::domain::{ServerDefinition, ServerLifecycle, ServerStatus, StartServerInteraction},
+    rcon::RconClient,
 };
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
-use serde_json::json;
-use time::OffsetDateTime;
-use tracing::info;
+use time::{Duration, OffsetDateTime};
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+/// How often readiness is checked while factorio is loading.
+const READY_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Time kept between the last edit of a reply and its token expiring.
+const TOKEN_MARGIN: Duration = Duration::seconds(30);
+
+/// Whether the token of an interaction made at `timestamp` can still edit its reply.
+fn token_usable(timestamp: OffsetDateTime) -> bool {
+    OffsetDateTime::now_utc() + TOKEN_MARGIN < timestamp + INTERACTION_TOKEN_LIFETIME
+}
 
 /// This is the main body for the function.
 /// Write your code inside it.
@@ -14,78 +34,417 @@ use tracing::info;
 /// - https://github.com/awslabs/aws-lambda-rust-runtime/tree/main/examples
 /// - https://github.com/aws-samples/serverless-rust-demo/
 async fn function_handler(
+    config: &Config,
+    webhook: &DiscordWebhook,
     ddb: &DynamoDBAccessor,
     service_accessor: &ServerAccessor,
+    cfn_accessor: &CfnAccessor,
     event: LambdaEvent<CloudWatchEvent>,
 ) -> Result<(), Error> {
     info!(?event.payload, "Received event");
-    let _detail = event.payload.detail.expect("No detail was provided");
-    let stack_status = _detail["status-details"]["status"]
+    // The request ID is in every log line of this invocation
+    let error_id = event.context.request_id;
+    let bad_event = |reason: &str| CrateError::BadInput(reason.to_string());
+    let detail = event
+        .payload
+        .detail
+        .ok_or_else(|| bad_event("No detail was provided"))?;
+    let stack_status = detail["status-details"]["status"]
+        .as_str()
+        .ok_or_else(|| bad_event("No stack status was provided"))?;
+    // The stack ID is an ARN of the form `arn:aws:cloudformation:...:stack/<name>/<uuid>`
+    let stack_name = detail["stack-id"]
         .as_str()
-        .expect("No stack status was provided");
+        .and_then(|stack_id| stack_id.split('/').nth(1))
+        .ok_or_else(|| bad_event("No stack ID was provided"))?;
+
+    let Some(server) = ddb
+        .list_servers()
+        .await?
+        .into_iter()
+        .find(|server| server.stack_name == stack_name)
+    else {
+        info!(stack_name, "Stack is not a registered server.");
+        return Ok(());
+    };
+
+    if !matches!(
+        stack_status,
+        "UPDATE_COMPLETE" | "UPDATE_FAILED" | "UPDATE_ROLLBACK_COMPLETE" | "UPDATE_ROLLBACK_FAILED"
+    ) {
+        return Ok(());
+    }
+
+    // Ties the update to the interaction that made it, if any
+    let request_token = cfn_accessor
+        .get_request_token(&server, stack_status)
+        .await?;
+
+    if stack_status == "UPDATE_COMPLETE" {
+        Ok(handle_stack_update(
+            config,
+            webhook,
+            ddb,
+            service_accessor,
+            cfn_accessor,
+            &server,
+            request_token.as_deref(),
+            &error_id,
+        )
+        .await?)
+    } else {
+        Ok(handle_stack_failure(
+            webhook,
+            ddb,
+            cfn_accessor,
+            &server,
+            stack_status,
+            request_token.as_deref(),
+        )
+        .await?)
+    }
+}
+
+/// Replaces the pending start or stop message with the reason the update
+/// failed, along with the starts that were waiting on the update. A failed
+/// start also closes the session it opened.
+///
+/// A failed update is followed by its rollback, so this can run twice for
+/// one update. The second run finds no pending interaction and does nothing.
+async fn handle_stack_failure(
+    webhook: &DiscordWebhook,
+    ddb: &DynamoDBAccessor,
+    cfn_accessor: &CfnAccessor,
+    server: &ServerDefinition,
+    stack_status: &str,
+    request_token: Option<&str>,
+) -> Result<()> {
+    let start = match request_token {
+        Some(request_token) => ddb.get_start(request_token).await?,
+        None => None,
+    };
+    let stop = match (request_token, &start) {
+        (Some(request_token), None) => ddb.get_stop(request_token).await?,
+        _ => None,
+    };
+    let others = other_pending_starts(ddb, server, start.as_ref()).await?;
+    if start.is_none() && stop.is_none() && others.is_empty() {
+        info!(?request_token, "No interaction is waiting on this update.");
+        return Ok(());
+    }
+
+    let failure = cfn_accessor.get_last_failure(server).await?;
+    info!(
+        ?start,
+        ?stop,
+        others = others.len(),
+        ?failure,
+        "Reporting failed stack update"
+    );
+    let report =
+        |action| present::server_failed(&server.name, action, stack_status, failure.as_ref());
 
-    match stack_status {
-        "UPDATE_COMPLETE" => Ok(handle_stack_update(ddb, service_accessor).await?),
-        _ => Ok(()),
+    if let Some(stop) = stop {
+        webhook.edit_original(&stop.token, &report("stop")).await?;
+        ddb.delete_interaction(stop).await?;
     }
+    if let Some(start) = &start {
+        // The start opened a session, which ends here without a running instance
+        if let Some(mut session) = ddb.get_open_session(&server.name).await? {
+            session.stopped_at = Some(OffsetDateTime::now_utc());
+            session.cost = Some(session.cost.unwrap_or_default());
+            ddb.put_session(&session).await?;
+        }
+        webhook
+            .edit_original(&start.token, &report("start"))
+            .await?;
+    }
+    edit_all(webhook, &others, &report("start")).await;
+
+    ddb.delete_interactions(start.into_iter().chain(others))
+        .await
+}
+
+/// Starts of the server still waiting on a stack update, other than `retrieved`.
+/// Starts made while the update was in progress wait on it too.
+async fn other_pending_starts(
+    ddb: &DynamoDBAccessor,
+    server: &ServerDefinition,
+    retrieved: Option<&StartServerInteraction>,
+) -> Result<Vec<StartServerInteraction>> {
+    Ok(ddb
+        .list_pending_starts(&server.name, OffsetDateTime::now_utc())
+        .await?
+        .into_iter()
+        .filter(|start| {
+            retrieved.is_none_or(|retrieved| retrieved.request_token != start.request_token)
+        })
+        .collect())
 }
 
-const APP_ID: &str = "1192583719236665424";
+/// Edits the reply of every start. One message that can no longer be edited
+/// should not hold up the rest.
+async fn edit_all(
+    webhook: &DiscordWebhook,
+    starts: &[StartServerInteraction],
+    message: &MessageData,
+) {
+    for start in starts {
+        if let Err(edit_err) = webhook.edit_original(&start.token, message).await {
+            warn!(?edit_err, ?start, "Could not update a pending start");
+        }
+    }
+}
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_stack_update(
+    config: &Config,
+    webhook: &DiscordWebhook,
+    ddb: &DynamoDBAccessor,
+    service_accessor: &ServerAccessor,
+    cfn_accessor: &CfnAccessor,
+    server: &ServerDefinition,
+    request_token: Option<&str>,
+    error_id: &str,
+) -> Result<()> {
+    let status = get_server_status(cfn_accessor, service_accessor, server).await?;
+    match status.lifecycle() {
+        ServerLifecycle::Starting | ServerLifecycle::Running => {
+            handle_start_complete(
+                config,
+                webhook,
+                ddb,
+                service_accessor,
+                cfn_accessor,
+                server,
+                &status,
+                request_token,
+                error_id,
+            )
+            .await
+        }
+        ServerLifecycle::Stopping | ServerLifecycle::Stopped => {
+            handle_stop_complete(webhook, ddb, server, request_token).await
+        }
+        lifecycle => {
+            info!(
+                ?lifecycle,
+                "Stack update neither started nor stopped the server."
+            );
+            Ok(())
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_start_complete(
+    config: &Config,
+    webhook: &DiscordWebhook,
     ddb: &DynamoDBAccessor,
     service_accessor: &ServerAccessor,
+    cfn_accessor: &CfnAccessor,
+    server: &ServerDefinition,
+    status: &ServerStatus,
+    request_token: Option<&str>,
+    error_id: &str,
 ) -> Result<()> {
-    let retrieved = ddb.get_latest_start().await?;
-    if retrieved.is_none() {
-        info!("No token was retrieved for this event.");
+    let retrieved = match request_token {
+        Some(request_token) => ddb.get_start(request_token).await?,
+        None => None,
+    };
+    let others = other_pending_starts(ddb, server, retrieved.as_ref()).await?;
+    if retrieved.is_none() && others.is_empty() {
+        info!(
+            ?request_token,
+            "No start interaction is waiting on this update."
+        );
         return Ok(());
     }
 
-    let ip = service_accessor
-        .get_running_server_ip()
-        .await?
-        .expect("Update was complete but no instance was running!");
+    info!(?retrieved, others = others.len(), "Retrieved tokens");
+    let waiting_since = OffsetDateTime::now_utc();
+    let mut deadline = waiting_since + config.ready_timeout;
+    if let Some(retrieved) = retrieved
+        .as_ref()
+        .filter(|start| token_usable(start.timestamp))
+    {
+        // Stop waiting in time to edit the reply with the outcome
+        deadline = deadline.min(retrieved.timestamp + INTERACTION_TOKEN_LIFETIME - TOKEN_MARGIN);
+    }
 
-    let retrieved = retrieved.unwrap();
-    let time_gap = OffsetDateTime::now_utc() - retrieved.timestamp;
+    let outcome = async {
+        // The update can complete before the ASG has put the instance in service
+        let ip = match status.instance_ip() {
+            Some(ip) => Some(ip.to_string()),
+            None => wait_for_instance(service_accessor, cfn_accessor, server, deadline).await?,
+        };
+        let Some(ip) = ip.as_deref() else {
+            warn!(
+                ?status,
+                "No instance came into service while waiting for it."
+            );
+            let waited = OffsetDateTime::now_utc() - waiting_since;
+            let message = present::server_not_ready(&server.name, None, waited);
+            edit_all(webhook, &others, &message).await;
+            return Ok(Some(message));
+        };
 
-    info!(?retrieved, "Retrieved token");
-    let url = format!(
-        "https://discord.com/api/v10/webhooks/{}/{}/messages/@original",
-        APP_ID, retrieved.token
-    );
-    info!(url, "Sending patch to URL");
-
-    reqwest::Client::new()
-        .patch(url)
-        .body(json!(
-            {
-                "content": "",
-                "embeds": [
-                    {
-                      "type": "rich",
-                      "title": "Starting the server!",
-                      "description": format!("Factorio has successfully launched in {}m {}s.", time_gap.whole_minutes(), time_gap.whole_seconds() % 60),
-                      "color": 0x1de302,
-                      "fields": [
-                        {
-                          "name": "Server IP",
-                          "value": format!("`{}`", ip),
-                          "inline": true
-                        }
-                      ],
-                      "thumbnail": {
-                        "url": "https://factorio.com/static/img/factorio-wheel.png",
-                        "height": 0,
-                        "width": 0
-                      }
-                    }
-                  ],
+        // Only the start that made the update waits for factorio to answer
+        edit_all(
+            webhook,
+            &others,
+            &present::already_starting(&server.name, ip),
+        )
+        .await;
+        let Some(retrieved) = &retrieved else {
+            return Ok(None);
+        };
+        if token_usable(retrieved.timestamp) {
+            webhook
+                .edit_original(&retrieved.token, &present::server_waiting(&server.name, ip))
+                .await?;
+        }
+
+        let ready =
+            wait_for_factorio(config, service_accessor, cfn_accessor, server, deadline).await?;
+        let message = if ready {
+            let time_gap = OffsetDateTime::now_utc() - retrieved.timestamp;
+            if let Some(mut session) = ddb.get_open_session(&server.name).await? {
+                session.ready_after = Some(time_gap);
+                ddb.put_session(&session).await?;
+            }
+            present::server_ready(&server.name, ip, time_gap)
+        } else {
+            let waited = OffsetDateTime::now_utc() - waiting_since;
+            present::server_not_ready(&server.name, Some(ip), waited)
+        };
+        Ok(Some(message))
+    }
+    .await;
+    // The reply says the server is loading, so a failure must replace it
+    let (message, result) = match outcome {
+        Ok(message) => (message, Ok(())),
+        Err(wait_err) => {
+            error!(?wait_err, error_id, "Could not wait for factorio");
+            // Edits and channel notices can not be ephemeral, the start reply is public
+            let message = MessageData {
+                flags: None,
+                ..present::error(&wait_err, error_id)
+            };
+            (Some(message), Err(wait_err))
+        }
+    };
+    if let (Some(retrieved), Some(message)) = (&retrieved, message) {
+        // A slow launch can outlive the token, then the channel is told instead
+        if token_usable(retrieved.timestamp) {
+            webhook.edit_original(&retrieved.token, &message).await?;
+        } else {
+            webhook.post_notice(&message).await?;
+        }
+    }
+
+    ddb.delete_interactions(retrieved.into_iter().chain(others))
+        .await?;
+    result
+}
+
+/// Polls until the server's instance is in service, and returns its IP. Gives
+/// up at `deadline`, or if the server stops starting in the meantime.
+async fn wait_for_instance(
+    service_accessor: &ServerAccessor,
+    cfn_accessor: &CfnAccessor,
+    server: &ServerDefinition,
+    deadline: OffsetDateTime,
+) -> Result<Option<String>> {
+    loop {
+        if OffsetDateTime::now_utc() + READY_POLL_INTERVAL > deadline {
+            return Ok(None);
+        }
+        sleep(READY_POLL_INTERVAL).await;
+
+        let status = get_server_status(cfn_accessor, service_accessor, server).await?;
+        match (status.lifecycle(), status.instance_ip()) {
+            (_, Some(ip)) => return Ok(Some(ip.to_string())),
+            (ServerLifecycle::Starting | ServerLifecycle::Running, None) => {
+                info!(?status.instance, "Waiting for the instance to be in service")
+            }
+            (lifecycle, None) => {
+                warn!(?lifecycle, "Server stopped starting while waiting for it");
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// Polls until the factorio task is running and, when RCON is enabled, answers
+/// a handshake. Gives up at `deadline`, or if the server stops starting in the
+/// meantime.
+async fn wait_for_factorio(
+    config: &Config,
+    service_accessor: &ServerAccessor,
+    cfn_accessor: &CfnAccessor,
+    server: &ServerDefinition,
+    deadline: OffsetDateTime,
+) -> Result<bool> {
+    loop {
+        let status = get_server_status(cfn_accessor, service_accessor, server).await?;
+        match (status.lifecycle(), status.running_ip(), &config.rcon) {
+            (ServerLifecycle::Running, _, None) => return Ok(true),
+            (ServerLifecycle::Running, Some(ip), Some(rcon)) => {
+                match RconClient::connect_to(ip, rcon).await {
+                    Ok(_) => return Ok(true),
+                    Err(rcon_err) => info!(?rcon_err, "Factorio is not answering yet"),
+                }
+            }
+            (ServerLifecycle::Starting | ServerLifecycle::Running, _, _) => {
+                info!("Waiting for the factorio task to run")
+            }
+            (lifecycle, _, _) => {
+                warn!(?lifecycle, "Server stopped starting while waiting for it");
+                return Ok(false);
             }
-        ).to_string())
-        .header(reqwest::header::CONTENT_TYPE, "application/json")
-        .send()
+        }
+
+        if OffsetDateTime::now_utc() + READY_POLL_INTERVAL > deadline {
+            return Ok(false);
+        }
+        sleep(READY_POLL_INTERVAL).await;
+    }
+}
+
+async fn handle_stop_complete(
+    webhook: &DiscordWebhook,
+    ddb: &DynamoDBAccessor,
+    server: &ServerDefinition,
+    request_token: Option<&str>,
+) -> Result<()> {
+    // Idle shutdowns have no interaction, but still end the session
+    let mut cost = None;
+    if let Some(mut session) = ddb.get_open_session(&server.name).await? {
+        session.stopped_at = Some(OffsetDateTime::now_utc());
+        ddb.put_session(&session).await?;
+        cost = session.cost;
+    }
+
+    let retrieved = match request_token {
+        Some(request_token) => ddb.get_stop(request_token).await?,
+        None => None,
+    };
+    let Some(retrieved) = retrieved else {
+        info!(?request_token, "No stop interaction made this update.");
+        return Ok(());
+    };
+
+    let uptime = retrieved
+        .launched_at
+        .map(|launched_at| OffsetDateTime::now_utc() - launched_at);
+
+    info!(?retrieved, "Retrieved token");
+    webhook
+        .edit_original(
+            &retrieved.token,
+            &present::server_stopped(&server.name, uptime, cost),
+        )
         .await?;
 
     ddb.delete_interaction(retrieved).await?;
@@ -105,10 +464,22 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let aws_config = aws_config::load_from_env().await;
-    let ddb = DynamoDBAccessor::new(&aws_config);
+    let config = Config::load(&aws_config).await?;
+
+    let webhook = DiscordWebhook::new(&config);
+    let ddb = DynamoDBAccessor::new(&aws_config, &config);
     let service_accessor = ServerAccessor::new(&aws_config);
+    let cfn_accessor = CfnAccessor::new(&aws_config);
     run(service_fn(|event: LambdaEvent<CloudWatchEvent>| async {
-        function_handler(&ddb, &service_accessor, event).await
+        function_handler(
+            &config,
+            &webhook,
+            &ddb,
+            &service_accessor,
+            &cfn_accessor,
+            event,
+        )
+        .await
     }))
     .await
 }
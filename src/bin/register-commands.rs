@@ -0,0 +1,50 @@
+//! Registers the `/factorio` command with Discord.
+//!
+//! Usage: `register-commands [--guild <guild id>] [--dry-run]`
+//!
+//! Reads `DISCORD_APP_ID` and `DISCORD_BOT_TOKEN` from the environment, and
+//! optionally `DISCORD_API_BASE`. With `--dry-run` the command definition is
+//! printed instead of being sent.
+
+use anyhow::{anyhow, Context, Result};
+use factorio_server_lambda::{
+    commands::CommandRegistry,
+    discord::api::{CommandScope, DiscordApi, DEFAULT_API_BASE},
+};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut scope = CommandScope::Global;
+    let mut dry_run = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--guild" => {
+                scope = CommandScope::Guild(args.next().ok_or(anyhow!("--guild needs an ID"))?)
+            }
+            "--dry-run" => dry_run = true,
+            unknown => return Err(anyhow!("Unknown argument {}", unknown)),
+        }
+    }
+
+    let commands = vec![CommandRegistry::new().definition()];
+    if dry_run {
+        println!("{}", serde_json::to_string_pretty(&commands)?);
+        return Ok(());
+    }
+
+    let api = DiscordApi::new(
+        &std::env::var("DISCORD_API_BASE").unwrap_or_else(|_| DEFAULT_API_BASE.to_string()),
+        &std::env::var("DISCORD_APP_ID").context("DISCORD_APP_ID is not set")?,
+        &std::env::var("DISCORD_BOT_TOKEN").context("DISCORD_BOT_TOKEN is not set")?,
+    );
+    api.register_commands(&scope, &commands).await?;
+
+    println!(
+        "Registered {} command(s) in {:?} scope",
+        commands.len(),
+        scope
+    );
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! The monthly budget, which caps what the servers may cost together.
+
+use time::OffsetDateTime;
+use tracing::info;
+
+use crate::{
+    aws_client::{compute::ServerAccessor, ddb::DynamoDBAccessor},
+    config::Config,
+    cost::month_to_date,
+    discord::{present, webhook::DiscordWebhook},
+    error::Result,
+    model::domain::{BudgetUsage, BudgetWarning},
+};
+
+/// What every server cost since the start of the month, up until `now`.
+pub async fn spent_this_month(
+    ddb: &DynamoDBAccessor,
+    server_accessor: &ServerAccessor,
+    now: OffsetDateTime,
+) -> Result<f64> {
+    let mut spent = 0.0;
+    for server in ddb.list_servers().await? {
+        spent += month_to_date(ddb, server_accessor, &server, now)
+            .await?
+            .total();
+    }
+    Ok(spent)
+}
+
+/// How much of this month's budget is spent. `None` when no budget is set.
+pub async fn usage(
+    ddb: &DynamoDBAccessor,
+    server_accessor: &ServerAccessor,
+    now: OffsetDateTime,
+) -> Result<Option<BudgetUsage>> {
+    let Some(budget) = ddb.get_budget().await? else {
+        return Ok(None);
+    };
+    Ok(Some(BudgetUsage {
+        amount: budget.amount,
+        spent: spent_this_month(ddb, server_accessor, now).await?,
+    }))
+}
+
+/// The highest of the ascending `thresholds` that usage has reached, unless
+/// it was already announced as `warned`.
+pub fn threshold_reached(
+    thresholds: &[u32],
+    usage: &BudgetUsage,
+    warned: Option<u32>,
+) -> Option<u32> {
+    let percent_used = usage.percent_used();
+    thresholds
+        .iter()
+        .copied()
+        .take_while(|&threshold| f64::from(threshold) <= percent_used)
+        .last()
+        .filter(|&threshold| warned.is_none_or(|warned| threshold > warned))
+}
+
+/// The month `now` is in, as stored with a budget warning, e.g. `2024-03`.
+fn month_key(now: OffsetDateTime) -> String {
+    format!("{}-{:02}", now.year(), u8::from(now.month()))
+}
+
+/// Posts a notice when spending crossed another threshold this month.
+pub async fn announce_usage(
+    config: &Config,
+    ddb: &DynamoDBAccessor,
+    server_accessor: &ServerAccessor,
+    webhook: &DiscordWebhook,
+) -> Result<()> {
+    let Some(mut budget) = ddb.get_budget().await? else {
+        return Ok(());
+    };
+    let now = OffsetDateTime::now_utc();
+    let month = month_key(now);
+    let usage = BudgetUsage {
+        amount: budget.amount,
+        spent: spent_this_month(ddb, server_accessor, now).await?,
+    };
+
+    // Warnings from earlier months don't count
+    let warned = budget
+        .warned
+        .as_ref()
+        .filter(|warned| warned.month == month)
+        .map(|warned| warned.percent);
+    let Some(percent) = threshold_reached(&config.budget_thresholds, &usage, warned) else {
+        return Ok(());
+    };
+
+    info!(percent, ?usage, "Budget threshold reached");
+    webhook
+        .post_notice(&present::budget_warning(percent, &usage))
+        .await?;
+    budget.warned = Some(BudgetWarning { month, percent });
+    ddb.put_budget(&budget).await
+}
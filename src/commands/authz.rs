@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::discord::{
+    model::Interaction,
+    present,
+    response::{InteractionResponse, MessageFlags},
+};
+
+/// Who may run a command. A member is allowed when they have any of the
+/// roles, or their user ID is listed.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CommandPolicy {
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub users: Vec<String>,
+}
+
+impl CommandPolicy {
+    pub fn allows(&self, interaction: &Interaction) -> bool {
+        let user_allowed = interaction
+            .invoker()
+            .is_some_and(|user| self.users.contains(&user.id));
+        let role_allowed = interaction
+            .member
+            .as_ref()
+            .is_some_and(|member| member.roles.iter().any(|role| self.roles.contains(role)));
+
+        user_allowed || role_allowed
+    }
+}
+
+/// Policies keyed by subcommand name, e.g.
+/// `{"stop": {"roles": ["<admin role id>"]}, "start": {"roles": ["<factorio role id>"]}}`.
+///
+/// Subcommands without a policy can be run by anyone.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct CommandPolicies(HashMap<String, CommandPolicy>);
+
+impl CommandPolicies {
+    pub fn is_authorized(&self, command: &str, interaction: &Interaction) -> bool {
+        self.0
+            .get(command)
+            .is_none_or(|policy| policy.allows(interaction))
+    }
+
+    /// The reply refusing the command, seen only by its invoker, unless they
+    /// may run it.
+    pub fn refusal(&self, command: &str, interaction: &Interaction) -> Option<InteractionResponse> {
+        if self.is_authorized(command, interaction) {
+            return None;
+        }
+        Some(InteractionResponse::message(
+            present::not_authorized(command).with_flags(MessageFlags::EPHEMERAL),
+        ))
+    }
+}
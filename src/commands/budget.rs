@@ -0,0 +1,65 @@
+use time::OffsetDateTime;
+use tracing::info;
+
+use super::{Command, CommandFuture, Context};
+use crate::{
+    budget,
+    discord::{
+        command::CommandOptionDefinition,
+        model::{CommandOption, Interaction},
+        present,
+    },
+    model::domain::Budget,
+};
+
+pub struct BudgetCommand;
+
+impl Command for BudgetCommand {
+    fn name(&self) -> &'static str {
+        "budget"
+    }
+
+    fn description(&self) -> &'static str {
+        "Shows how much of the monthly budget is spent, or sets the budget"
+    }
+
+    fn options(&self) -> Vec<CommandOptionDefinition> {
+        vec![CommandOptionDefinition::number(
+            "amount",
+            "New monthly budget in US dollars, admins only",
+        )]
+    }
+
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a Context,
+        interaction: &'a Interaction,
+        options: &'a CommandOption,
+    ) -> CommandFuture<'a> {
+        Box::pin(async move {
+            let Some(amount) = options.get_number("amount") else {
+                let usage =
+                    budget::usage(&ctx.ddb, &ctx.server_accessor, OffsetDateTime::now_utc())
+                        .await?;
+                return Ok(present::budget_status(usage.as_ref()));
+            };
+
+            if !ctx.config.budget_admins.allows(interaction) {
+                return Ok(present::not_authorized("budget amount"));
+            }
+            if !amount.is_finite() || amount <= 0.0 {
+                return Ok(present::invalid_budget(amount));
+            }
+
+            // Thresholds are announced again against the new amount
+            info!(amount, "Setting the monthly budget");
+            ctx.ddb
+                .put_budget(&Budget {
+                    amount,
+                    warned: None,
+                })
+                .await?;
+            Ok(present::budget_set(amount))
+        })
+    }
+}
@@ -0,0 +1,46 @@
+use time::OffsetDateTime;
+
+use super::{server_option, Command, CommandFuture, Context};
+use crate::{
+    cost::month_to_date,
+    discord::{
+        command::CommandOptionDefinition,
+        model::{CommandOption, Interaction},
+        present,
+    },
+};
+
+pub struct CostCommand;
+
+impl Command for CostCommand {
+    fn name(&self) -> &'static str {
+        "cost"
+    }
+
+    fn description(&self) -> &'static str {
+        "Shows what the server cost so far this month"
+    }
+
+    fn options(&self) -> Vec<CommandOptionDefinition> {
+        vec![server_option()]
+    }
+
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a Context,
+        _interaction: &'a Interaction,
+        options: &'a CommandOption,
+    ) -> CommandFuture<'a> {
+        Box::pin(async move {
+            let server = match ctx.server(options).await? {
+                Ok(server) => server,
+                Err(reply) => return Ok(reply),
+            };
+            let now = OffsetDateTime::now_utc();
+            let cost = month_to_date(&ctx.ddb, &ctx.server_accessor, &server, now).await?;
+
+            let month = format!("{} {}", now.month(), now.year());
+            Ok(present::cost_summary(&server.name, &month, &cost))
+        })
+    }
+}
@@ -0,0 +1,54 @@
+use super::{server_option, Command, CommandFuture, Context};
+use crate::discord::{
+    command::CommandOptionDefinition,
+    model::{CommandOption, Interaction},
+    present,
+};
+
+/// Sessions shown per page, which keeps the embed well below Discord's limits.
+const PAGE_SIZE: usize = 10;
+
+pub struct HistoryCommand;
+
+impl Command for HistoryCommand {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
+    fn description(&self) -> &'static str {
+        "Lists when the server ran, and who started and stopped it"
+    }
+
+    fn options(&self) -> Vec<CommandOptionDefinition> {
+        vec![
+            server_option(),
+            CommandOptionDefinition::integer("page", "Page of older sessions to show, from 1"),
+        ]
+    }
+
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a Context,
+        _interaction: &'a Interaction,
+        options: &'a CommandOption,
+    ) -> CommandFuture<'a> {
+        Box::pin(async move {
+            let server = match ctx.server(options).await? {
+                Ok(server) => server,
+                Err(reply) => return Ok(reply),
+            };
+            let page = options.get_int("page").unwrap_or(1).max(1) as usize;
+
+            // One extra session tells whether there is a next page
+            let mut sessions = ctx
+                .ddb
+                .list_sessions(&server.name, page * PAGE_SIZE + 1)
+                .await?;
+            let has_more = sessions.len() > page * PAGE_SIZE;
+            sessions.truncate(page * PAGE_SIZE);
+            let shown = sessions.split_off(((page - 1) * PAGE_SIZE).min(sessions.len()));
+
+            Ok(present::history(&server.name, page, &shown, has_more))
+        })
+    }
+}
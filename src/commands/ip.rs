@@ -0,0 +1,39 @@
+use super::{server_option, Command, CommandFuture, Context};
+use crate::discord::{
+    command::CommandOptionDefinition,
+    model::{CommandOption, Interaction},
+    present,
+};
+
+pub struct IpCommand;
+
+impl Command for IpCommand {
+    fn name(&self) -> &'static str {
+        "ip"
+    }
+
+    fn description(&self) -> &'static str {
+        "Gets the IP of the server"
+    }
+
+    fn options(&self) -> Vec<CommandOptionDefinition> {
+        vec![server_option()]
+    }
+
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a Context,
+        _interaction: &'a Interaction,
+        options: &'a CommandOption,
+    ) -> CommandFuture<'a> {
+        Box::pin(async move {
+            let server = match ctx.server(options).await? {
+                Ok(server) => server,
+                Err(reply) => return Ok(reply),
+            };
+
+            let status = ctx.server_status(&server).await?;
+            Ok(present::server_ip(&server.name, &status.ip_status()))
+        })
+    }
+}
@@ -0,0 +1,231 @@
+//! The `/factorio` subcommands, shared by the command Lambda, which validates
+//! and autocompletes them, and the worker Lambda, which runs them.
+
+use std::{future::Future, pin::Pin};
+
+use crate::{
+    aws_client::{
+        cfn::CfnAccessor, compute::ServerAccessor, ddb::DynamoDBAccessor, get_server_status,
+        saves::SaveAccessor,
+    },
+    config::Config,
+    discord::{
+        command::{ApplicationCommand, CommandOptionDefinition},
+        model::{CommandOption, CommandOptionType, Interaction},
+        response::{CommandOptionChoice, MessageData},
+        webhook::DiscordWebhook,
+    },
+    error::{Error, Result},
+    model::domain::{ServerDefinition, ServerStatus},
+};
+
+pub mod authz;
+pub mod budget;
+pub mod cost;
+pub mod history;
+pub mod ip;
+pub mod players;
+pub mod start;
+pub mod status;
+pub mod stop;
+
+pub type CommandFuture<'a> = Pin<Box<dyn Future<Output = Result<MessageData>> + Send + 'a>>;
+pub type AutocompleteFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Vec<CommandOptionChoice>>> + Send + 'a>>;
+
+/// Everything a command needs to do its work.
+pub struct Context {
+    pub config: Config,
+    pub server_accessor: ServerAccessor,
+    pub cfn_accessor: CfnAccessor,
+    pub ddb: DynamoDBAccessor,
+    pub saves: SaveAccessor,
+    pub webhook: DiscordWebhook,
+}
+
+impl Context {
+    pub fn new(sdk_config: &aws_config::SdkConfig, config: Config) -> Self {
+        Context {
+            server_accessor: ServerAccessor::new(sdk_config),
+            cfn_accessor: CfnAccessor::new(sdk_config),
+            ddb: DynamoDBAccessor::new(sdk_config, &config),
+            saves: SaveAccessor::new(sdk_config, &config),
+            webhook: DiscordWebhook::new(&config),
+            config,
+        }
+    }
+
+    /// Looks up the server named by the `server` option, or the default one.
+    ///
+    /// Returns the message to reply with when no such server is registered.
+    pub async fn server(
+        &self,
+        options: &CommandOption,
+    ) -> Result<Result<ServerDefinition, MessageData>> {
+        let name = options
+            .get_str(SERVER_OPTION)
+            .unwrap_or(&self.config.default_server);
+
+        Ok(self
+            .ddb
+            .get_server(name)
+            .await?
+            .ok_or_else(|| crate::discord::present::unknown_server(name)))
+    }
+
+    /// Takes a snapshot of the server, to derive its lifecycle from.
+    pub async fn server_status(&self, server: &ServerDefinition) -> Result<ServerStatus> {
+        get_server_status(&self.cfn_accessor, &self.server_accessor, server).await
+    }
+}
+
+/// Who ran the command, as recorded in the session history.
+pub fn invoker_name(interaction: &Interaction) -> String {
+    interaction
+        .invoker()
+        .map_or("someone", |user| user.display_name())
+        .to_string()
+}
+
+/// The `ClientRequestToken` of the stack update an interaction makes, which
+/// lets the update-complete Lambda trace the update back to the interaction.
+pub fn request_token(interaction: &Interaction) -> String {
+    format!("discord-{}", interaction.id)
+}
+
+const SERVER_OPTION: &str = "server";
+
+/// The `server` option taken by every command that targets a single server.
+pub fn server_option() -> CommandOptionDefinition {
+    CommandOptionDefinition::string(
+        SERVER_OPTION,
+        "Which server to use, defaults to the main server",
+    )
+}
+
+/// A `/factorio` subcommand.
+pub trait Command: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn options(&self) -> Vec<CommandOptionDefinition>;
+
+    /// Runs the command, returning the message that replaces the deferred response.
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a Context,
+        interaction: &'a Interaction,
+        options: &'a CommandOption,
+    ) -> CommandFuture<'a>;
+
+    /// Suggests values for the option the user is currently typing in.
+    fn autocomplete<'a>(
+        &'a self,
+        _ctx: &'a Context,
+        _options: &'a CommandOption,
+    ) -> AutocompleteFuture<'a> {
+        Box::pin(async { Ok(vec![]) })
+    }
+}
+
+/// All known subcommands of the `/factorio` command.
+pub struct CommandRegistry {
+    commands: Vec<Box<dyn Command>>,
+}
+
+impl CommandRegistry {
+    pub const NAME: &'static str = "factorio";
+    pub const DESCRIPTION: &'static str = "Control factorio";
+
+    pub fn new() -> Self {
+        CommandRegistry {
+            commands: vec![
+                Box::new(start::StartCommand),
+                Box::new(stop::StopCommand),
+                Box::new(ip::IpCommand),
+                Box::new(players::PlayersCommand),
+                Box::new(status::StatusCommand),
+                Box::new(history::HistoryCommand),
+                Box::new(cost::CostCommand),
+                Box::new(budget::BudgetCommand),
+            ],
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&dyn Command> {
+        self.commands
+            .iter()
+            .find(|command| command.name() == name)
+            .map(|command| command.as_ref())
+    }
+
+    /// The application command definition to register with Discord.
+    pub fn definition(&self) -> ApplicationCommand {
+        ApplicationCommand {
+            name: Self::NAME.to_string(),
+            description: Self::DESCRIPTION.to_string(),
+            options: self
+                .commands
+                .iter()
+                .map(|command| CommandOptionDefinition {
+                    options: command.options(),
+                    ..CommandOptionDefinition::new(
+                        CommandOptionType::SubCommand,
+                        command.name(),
+                        command.description(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Checks that the invoked subcommand exists and has its required options.
+    pub fn validate(&self, options: &CommandOption) -> Result<(), String> {
+        let command = self
+            .find(&options.name)
+            .ok_or_else(|| format!("Unknown subcommand {}", options.name))?;
+
+        match command
+            .options()
+            .into_iter()
+            .find(|definition| definition.required && options.option(&definition.name).is_none())
+        {
+            Some(missing) => Err(format!("Missing {} option", missing.name)),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn dispatch(&self, ctx: &Context, interaction: &Interaction) -> Result<MessageData> {
+        let options = subcommand(interaction)?;
+        let command = self
+            .find(&options.name)
+            .ok_or_else(|| Error::BadInput(format!("Unknown subcommand {}", options.name)))?;
+
+        command.handle(ctx, interaction, options).await
+    }
+
+    pub async fn autocomplete(
+        &self,
+        ctx: &Context,
+        interaction: &Interaction,
+    ) -> Result<Vec<CommandOptionChoice>> {
+        let options = subcommand(interaction)?;
+        match self.find(&options.name) {
+            Some(command) => command.autocomplete(ctx, options).await,
+            None => Ok(vec![]),
+        }
+    }
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        CommandRegistry::new()
+    }
+}
+
+fn subcommand(interaction: &Interaction) -> Result<&CommandOption> {
+    interaction
+        .data
+        .as_ref()
+        .and_then(|data| data.subcommand())
+        .ok_or_else(|| Error::BadInput("Missing subcommand".to_string()))
+}
@@ -0,0 +1,64 @@
+use tracing::warn;
+
+use super::{server_option, Command, CommandFuture, Context};
+use crate::{
+    discord::{
+        command::CommandOptionDefinition,
+        model::{CommandOption, Interaction},
+        present,
+    },
+    model::domain::PlayersStatus,
+    rcon::RconClient,
+};
+
+pub struct PlayersCommand;
+
+impl Command for PlayersCommand {
+    fn name(&self) -> &'static str {
+        "players"
+    }
+
+    fn description(&self) -> &'static str {
+        "Shows who is playing on the server"
+    }
+
+    fn options(&self) -> Vec<CommandOptionDefinition> {
+        vec![server_option()]
+    }
+
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a Context,
+        _interaction: &'a Interaction,
+        options: &'a CommandOption,
+    ) -> CommandFuture<'a> {
+        Box::pin(async move {
+            let server = match ctx.server(options).await? {
+                Ok(server) => server,
+                Err(reply) => return Ok(reply),
+            };
+
+            let server_status = ctx.server_status(&server).await?;
+            let status = match (server_status.running_ip(), &ctx.config.rcon) {
+                (None, _) => PlayersStatus::NotRunning,
+                (Some(_), None) => PlayersStatus::RconDisabled,
+                (Some(ip), Some(rcon)) => match RconClient::connect_to(ip, rcon).await {
+                    Ok(mut client) => {
+                        let (total, online) = if rcon.allow_lua {
+                            client.online_players_with_play_time().await?
+                        } else {
+                            client.online_players().await?
+                        };
+                        PlayersStatus::Online { total, online }
+                    }
+                    Err(rcon_err) => {
+                        warn!(?rcon_err, server.name, "Could not reach RCON");
+                        PlayersStatus::Unreachable
+                    }
+                },
+            };
+
+            Ok(present::players(&server.name, &status))
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use time::OffsetDateTime;
+use tracing::info;
+
+use super::{
+    invoker_name, request_token, server_option, AutocompleteFuture, Command, CommandFuture, Context,
+};
+use crate::{
+    aws_client::{SaveCatalog, ServerUpdater},
+    budget,
+    discord::{
+        command::CommandOptionDefinition,
+        model::{CommandOption, Interaction, OptionValue},
+        present,
+        response::CommandOptionChoice,
+    },
+    error::Error,
+    model::domain::{ServerLifecycle, Session, StartServerInteraction, UpdateOutcome},
+};
+
+/// Discord allows at most 25 autocomplete choices.
+const MAX_CHOICES: usize = 25;
+
+pub struct StartCommand;
+
+impl Command for StartCommand {
+    fn name(&self) -> &'static str {
+        "start"
+    }
+
+    fn description(&self) -> &'static str {
+        "Starts the server"
+    }
+
+    fn options(&self) -> Vec<CommandOptionDefinition> {
+        vec![
+            CommandOptionDefinition::string("save", "What save to load")
+                .required()
+                .autocomplete(),
+            server_option(),
+            CommandOptionDefinition::string(
+                "size",
+                "Instance size to run on, keeps the last one when left out",
+            )
+            .autocomplete(),
+            CommandOptionDefinition::boolean(
+                "override",
+                "Start even though the monthly budget is spent, admins only",
+            ),
+        ]
+    }
+
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a Context,
+        interaction: &'a Interaction,
+        options: &'a CommandOption,
+    ) -> CommandFuture<'a> {
+        Box::pin(async move {
+            let server = match ctx.server(options).await? {
+                Ok(server) => server,
+                Err(reply) => return Ok(reply),
+            };
+            let mount_dir = options
+                .get_str("save")
+                .ok_or_else(|| Error::BadInput("Missing save option".to_string()))?;
+
+            // Reject unknown saves and sizes before CloudFormation is touched
+            if !ctx.saves.save_exists(&server, mount_dir).await? {
+                return Ok(present::unknown_save(mount_dir));
+            }
+            let size_name = options.get_str("size");
+            let size = match size_name {
+                Some(name) => match ctx.config.instance_sizes.get(name) {
+                    Some(size) => Some(size),
+                    None => {
+                        return Ok(present::unknown_size(
+                            name,
+                            ctx.config.instance_sizes.keys(),
+                        ))
+                    }
+                },
+                None => None,
+            };
+
+            let status = ctx.server_status(&server).await?;
+            let lifecycle = status.lifecycle();
+            if !lifecycle.can_transition_to(ServerLifecycle::Starting) {
+                return Ok(present::lifecycle_conflict(
+                    &server.name,
+                    lifecycle,
+                    "start",
+                ));
+            }
+
+            let now = OffsetDateTime::now_utc();
+            if let Some(usage) = budget::usage(&ctx.ddb, &ctx.server_accessor, now).await? {
+                if usage.is_exhausted() {
+                    if !options.get_bool("override").unwrap_or(false) {
+                        return Ok(present::budget_exhausted(&usage));
+                    }
+                    if !ctx.config.budget_admins.allows(interaction) {
+                        return Ok(present::budget_override_denied());
+                    }
+                    info!(?usage, "Starting despite the spent budget");
+                }
+            }
+
+            let request_token = request_token(interaction);
+            let outcome = ctx
+                .cfn_accessor
+                .start_server(&server, mount_dir, size, &request_token)
+                .await?;
+            // A start during another update is told when that one completes
+            if outcome != UpdateOutcome::AlreadyInState {
+                ctx.ddb
+                    .save_interaction(StartServerInteraction {
+                        server: server.name.clone(),
+                        token: interaction.token.clone(),
+                        timestamp: now,
+                        request_token,
+                    })
+                    .await?;
+            }
+            if outcome == UpdateOutcome::Updating {
+                ctx.ddb
+                    .put_session(&Session {
+                        server: server.name.clone(),
+                        started_at: now,
+                        started_by: invoker_name(interaction),
+                        save: mount_dir.to_string(),
+                        instance_type: size
+                            .map(|size| size.instance_type.clone())
+                            .or(status.stack.instance_type),
+                        ready_after: None,
+                        stopped_by: None,
+                        stopped_at: None,
+                        cost: None,
+                        interrupted: vec![],
+                    })
+                    .await?;
+            }
+            Ok(present::start_server(
+                outcome,
+                &server.name,
+                mount_dir,
+                size_name,
+            ))
+        })
+    }
+
+    fn autocomplete<'a>(
+        &'a self,
+        ctx: &'a Context,
+        options: &'a CommandOption,
+    ) -> AutocompleteFuture<'a> {
+        Box::pin(async move {
+            let Some(focused) = options.focused() else {
+                return Ok(vec![]);
+            };
+            let typed = match &focused.value {
+                Some(OptionValue::String(typed)) => typed.to_lowercase(),
+                _ => String::new(),
+            };
+
+            let candidates = match focused.name.as_str() {
+                "save" => match ctx.server(options).await? {
+                    Ok(server) => ctx.saves.list_saves(&server).await?,
+                    Err(_) => return Ok(vec![]),
+                },
+                "size" => ctx.config.instance_sizes.keys().cloned().collect(),
+                _ => return Ok(vec![]),
+            };
+
+            Ok(candidates
+                .into_iter()
+                .filter(|candidate| candidate.to_lowercase().contains(&typed))
+                .take(MAX_CHOICES)
+                .map(CommandOptionChoice::new)
+                .collect())
+        })
+    }
+}
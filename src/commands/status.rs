@@ -0,0 +1,46 @@
+use time::OffsetDateTime;
+
+use super::{server_option, Command, CommandFuture, Context};
+use crate::discord::{
+    command::CommandOptionDefinition,
+    model::{CommandOption, Interaction},
+    present,
+};
+
+pub struct StatusCommand;
+
+impl Command for StatusCommand {
+    fn name(&self) -> &'static str {
+        "status"
+    }
+
+    fn description(&self) -> &'static str {
+        "Shows the state of the server's stack, instance and container"
+    }
+
+    fn options(&self) -> Vec<CommandOptionDefinition> {
+        vec![server_option()]
+    }
+
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a Context,
+        _interaction: &'a Interaction,
+        options: &'a CommandOption,
+    ) -> CommandFuture<'a> {
+        Box::pin(async move {
+            let server = match ctx.server(options).await? {
+                Ok(server) => server,
+                Err(reply) => return Ok(reply),
+            };
+
+            let status = ctx.server_status(&server).await?;
+
+            Ok(present::status(
+                &server.name,
+                &status,
+                OffsetDateTime::now_utc(),
+            ))
+        })
+    }
+}
@@ -0,0 +1,214 @@
+use time::{Duration, OffsetDateTime};
+use tracing::{info, warn};
+
+use super::{invoker_name, request_token, server_option, Command, CommandFuture, Context};
+use crate::{
+    aws_client::ServerUpdater,
+    budget,
+    config::RconSettings,
+    cost,
+    discord::{
+        command::CommandOptionDefinition,
+        model::{CommandOption, Interaction},
+        present,
+        response::MessageData,
+    },
+    error::Result,
+    model::domain::{
+        ServerDefinition, ServerLifecycle, ServerStatus, StopProgress, StopServerInteraction,
+        UpdateOutcome,
+    },
+    rcon::{RconClient, RconError},
+};
+
+/// Moments of the countdown at which players are reminded, besides its start.
+const REMINDERS: [i64; 3] = [60, 30, 10];
+
+pub struct StopCommand;
+
+impl Command for StopCommand {
+    fn name(&self) -> &'static str {
+        "stop"
+    }
+
+    fn description(&self) -> &'static str {
+        "Warns the players, saves the game and stops the server"
+    }
+
+    fn options(&self) -> Vec<CommandOptionDefinition> {
+        vec![
+            server_option(),
+            CommandOptionDefinition::boolean(
+                "force",
+                "Stop right away, without warning players or saving",
+            ),
+        ]
+    }
+
+    fn handle<'a>(
+        &'a self,
+        ctx: &'a Context,
+        interaction: &'a Interaction,
+        options: &'a CommandOption,
+    ) -> CommandFuture<'a> {
+        Box::pin(async move {
+            let server = match ctx.server(options).await? {
+                Ok(server) => server,
+                Err(reply) => return Ok(reply),
+            };
+
+            let status = ctx.server_status(&server).await?;
+            let lifecycle = status.lifecycle();
+            if !lifecycle.can_transition_to(ServerLifecycle::Stopping) {
+                return Ok(present::lifecycle_conflict(&server.name, lifecycle, "stop"));
+            }
+
+            if options.get_bool("force").unwrap_or(false) {
+                info!(server.name, "Force stopping server");
+                let outcome = stop_stack(ctx, interaction, &server, &status).await?;
+                return Ok(present::stop_server(outcome, &server.name, false));
+            }
+
+            let Some(ip) = status.running_ip() else {
+                // Factorio is not running, so there is nothing to save
+                let outcome = stop_stack(ctx, interaction, &server, &status).await?;
+                return Ok(present::stop_server(outcome, &server.name, false));
+            };
+            let Some(rcon) = &ctx.config.rcon else {
+                let outcome = stop_stack(ctx, interaction, &server, &status).await?;
+                return Ok(present::stop_without_save(outcome, &server.name));
+            };
+
+            // The stack is only stopped once the save is written
+            match save_game(ctx, interaction, &server, ip, rcon).await {
+                Ok(()) => {
+                    let outcome = stop_stack(ctx, interaction, &server, &status).await?;
+                    Ok(present::stop_server(outcome, &server.name, true))
+                }
+                Err(rcon_err) => {
+                    warn!(
+                        ?rcon_err,
+                        server.name, "Could not save, leaving the server running"
+                    );
+                    Ok(present::save_failed(&server.name))
+                }
+            }
+        })
+    }
+}
+
+/// Stops the stack, and remembers the interaction so the update-complete
+/// Lambda can report when the server is down.
+async fn stop_stack(
+    ctx: &Context,
+    interaction: &Interaction,
+    server: &ServerDefinition,
+    status: &ServerStatus,
+) -> Result<UpdateOutcome> {
+    let request_token = request_token(interaction);
+    let outcome = ctx
+        .cfn_accessor
+        .stop_server(server, Some(&request_token))
+        .await?;
+    if outcome == UpdateOutcome::Updating {
+        let launched_at = status
+            .instance
+            .as_ref()
+            .and_then(|instance| instance.launched_at);
+        ctx.ddb
+            .save_interaction(StopServerInteraction {
+                server: server.name.clone(),
+                token: interaction.token.clone(),
+                timestamp: OffsetDateTime::now_utc(),
+                request_token,
+                launched_at,
+            })
+            .await?;
+        cost::settle_session(
+            &ctx.ddb,
+            &ctx.server_accessor,
+            server,
+            &invoker_name(interaction),
+            launched_at,
+        )
+        .await?;
+        // The stop went through, a failed notice should not report otherwise
+        if let Err(budget_err) =
+            budget::announce_usage(&ctx.config, &ctx.ddb, &ctx.server_accessor, &ctx.webhook).await
+        {
+            warn!(?budget_err, "Could not check the budget");
+        }
+    }
+    Ok(outcome)
+}
+
+/// Counts down in-game, then saves and waits for the save to be written.
+async fn save_game(
+    ctx: &Context,
+    interaction: &Interaction,
+    server: &ServerDefinition,
+    ip: &str,
+    rcon: &RconSettings,
+) -> Result<(), RconError> {
+    let mut client = RconClient::connect_to(ip, rcon).await?;
+
+    let countdown = ctx.config.stop_countdown;
+    let mut checkpoints = vec![countdown];
+    checkpoints.extend(
+        REMINDERS
+            .iter()
+            .map(|seconds| Duration::seconds(*seconds))
+            .filter(|reminder| *reminder < countdown),
+    );
+
+    for (i, remaining) in checkpoints.iter().enumerate() {
+        if remaining.is_positive() {
+            client
+                .broadcast(&format!(
+                    "The server is shutting down in {} seconds, the game will be saved.",
+                    remaining.whole_seconds()
+                ))
+                .await?;
+        }
+        if i == 0 {
+            report(
+                ctx,
+                interaction,
+                present::stop_progress(
+                    &server.name,
+                    StopProgress::Warning {
+                        remaining: *remaining,
+                    },
+                ),
+            )
+            .await;
+        }
+
+        let next = checkpoints.get(i + 1).copied().unwrap_or(Duration::ZERO);
+        tokio::time::sleep((*remaining - next).unsigned_abs()).await;
+    }
+
+    client
+        .broadcast("Saving the game, the server is shutting down.")
+        .await?;
+    report(
+        ctx,
+        interaction,
+        present::stop_progress(&server.name, StopProgress::Saving),
+    )
+    .await;
+    client.server_save_and_wait().await?;
+    info!(server.name, "Game saved");
+    Ok(())
+}
+
+/// Progress updates are best effort, the stop goes ahead if Discord is unreachable.
+async fn report(ctx: &Context, interaction: &Interaction, message: MessageData) {
+    if let Err(webhook_err) = ctx
+        .webhook
+        .edit_original(&interaction.token, &message)
+        .await
+    {
+        warn!(?webhook_err, "Could not report stop progress");
+    }
+}
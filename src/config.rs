@@ -0,0 +1,361 @@
+//! Deployment settings, read from SSM and environment variables.
+//!
+//! # Migrating to the server registry
+//!
+//! Servers are looked up by name in the `FACTORIO_SERVERS_TABLE` registry.
+//! Deployments from before the registry keep working without it: the server
+//! named by `FACTORIO_DEFAULT_SERVER` falls back to the resources set with
+//! `FACTORIO_STACK_NAME`, `FACTORIO_CLUSTER_NAME`, `FACTORIO_SERVICE_NAME` and
+//! `FACTORIO_ASG_NAME`, for as long as the registry has no entry of that name.
+//! To migrate, put an item with that `name` and the same `stack_name` in the
+//! registry, then add more servers next to it.
+//!
+//! # Migrating the interactions table
+//!
+//! `FACTORIO_INTERACTIONS_TABLE` is keyed by the `command` hash key and the
+//! `request_token` range key, a string. Tables keyed by the numeric
+//! `timestamp` range key have to be recreated, which only drops replies that
+//! are still waiting on a stack update.
+//!
+//! # Migrating saves
+//!
+//! Saves are kept per server, so existing saves have to be moved from
+//! `<FACTORIO_SAVES_PREFIX>/<save>/` to `<FACTORIO_SAVES_PREFIX>/<server>/<save>/`.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+use aws_sdk_ssm::{error::SdkError, operation::get_parameters_by_path::GetParametersByPathError};
+use ed25519_dalek::{VerifyingKey, PUBLIC_KEY_LENGTH};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+use time::Duration;
+use tracing::info;
+
+use crate::{
+    commands::authz::{CommandPolicies, CommandPolicy},
+    discord::api::DEFAULT_API_BASE,
+    model::domain::{InstanceSize, ServerDefinition},
+    rcon,
+};
+
+/// Name of the environment variable pointing at an SSM Parameter Store path.
+/// Every parameter under that path is loaded, using the last segment of its
+/// name as the setting key, e.g. `/factorio/staging/FACTORIO_SERVERS_TABLE`.
+const SSM_PREFIX_VAR: &str = "FACTORIO_SSM_PREFIX";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Missing required setting {0}")]
+    Missing(&'static str),
+    #[error("Invalid value for {key}: {reason}")]
+    Invalid { key: &'static str, reason: String },
+    #[error("Could not load parameters from SSM")]
+    Ssm(#[source] Box<SdkError<GetParametersByPathError>>),
+}
+
+/// Deployment specific settings, loaded once at cold start.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Server used when a command does not name one.
+    pub default_server: String,
+    /// The default server's resources while the registry has no entry for it,
+    /// as configured before servers were registered.
+    pub fallback_server: ServerDefinition,
+    pub servers_table: String,
+    pub interactions_table: String,
+    /// Bucket and prefix under which each server's saves are stored as
+    /// directories, e.g. `saves/<server>/<save>/`. Only `start` needs the
+    /// bucket, so the other Lambdas can run without one.
+    pub saves_bucket: Option<String>,
+    pub saves_prefix: String,
+    pub worker_function_name: String,
+    /// Which roles and users may run each subcommand.
+    pub command_policies: CommandPolicies,
+    /// The sizes `start` may pick from, by name. Ordered so they list predictably.
+    pub instance_sizes: BTreeMap<String, InstanceSize>,
+    /// How to reach factorio over RCON. `None` when RCON is disabled.
+    pub rcon: Option<RconSettings>,
+    /// How long players are warned in-game before a graceful stop.
+    /// The worker Lambda's timeout must be longer than this.
+    pub stop_countdown: Duration,
+    /// How long to wait for factorio to answer after the instance is up. The
+    /// wait is cut short so the reply is edited before its token expires.
+    /// The update-complete Lambda's timeout must be longer than this.
+    pub ready_timeout: Duration,
+    pub idle_table: String,
+    /// History of server sessions, keyed by server and start time.
+    pub sessions_table: String,
+    /// History of spot interruptions, keyed by server and time.
+    pub interruptions_table: String,
+    /// How long a server may run without players before it is shut down.
+    pub idle_shutdown: Duration,
+    /// Holds the monthly budget, which is set with `/factorio budget`.
+    pub budget_table: String,
+    /// Percentages of the budget at which a usage warning is posted, ascending.
+    pub budget_thresholds: Vec<u32>,
+    /// Who may change the budget, and start a server once it is spent.
+    pub budget_admins: CommandPolicy,
+    pub discord_app_id: String,
+    pub discord_api_base: String,
+    pub discord_public_key: VerifyingKey,
+    /// Channel webhook that server notices are posted to.
+    pub discord_notify_webhook: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct RconSettings {
+    pub port: u16,
+    pub password: String,
+    /// Whether commands may run Lua, e.g. to show play time. Lua disables
+    /// achievements on the save, so this is off unless explicitly enabled.
+    pub allow_lua: bool,
+}
+
+impl std::fmt::Debug for RconSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RconSettings")
+            .field("port", &self.port)
+            .field("allow_lua", &self.allow_lua)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Config {
+    /// Loads the configuration from SSM, if `FACTORIO_SSM_PREFIX` is set, and
+    /// from environment variables. Environment variables take precedence.
+    pub async fn load(sdk_config: &aws_config::SdkConfig) -> Result<Config, ConfigError> {
+        let mut values = HashMap::new();
+
+        if let Ok(prefix) = std::env::var(SSM_PREFIX_VAR) {
+            values.extend(load_ssm_parameters(sdk_config, &prefix).await?);
+        }
+        values.extend(std::env::vars());
+
+        Config::from_values(&values)
+    }
+
+    /// Builds and validates the configuration from raw key/value settings.
+    pub fn from_values(values: &HashMap<String, String>) -> Result<Config, ConfigError> {
+        let default_server =
+            optional(values, "FACTORIO_DEFAULT_SERVER")?.unwrap_or_else(|| "factorio".to_string());
+        // Only the stack name is needed, as long as the other resources follow
+        // the naming of the CloudFormation template.
+        let stack_name = optional(values, "FACTORIO_STACK_NAME")?
+            .unwrap_or_else(|| "factorio-ecs-spot".to_string());
+
+        let mut config = Config {
+            fallback_server: ServerDefinition {
+                name: default_server.clone(),
+                cluster_name: optional(values, "FACTORIO_CLUSTER_NAME")?
+                    .unwrap_or_else(|| format!("{}-cluster", stack_name)),
+                service_name: optional(values, "FACTORIO_SERVICE_NAME")?
+                    .unwrap_or_else(|| format!("{}-ecs-service", stack_name)),
+                asg_name: optional(values, "FACTORIO_ASG_NAME")?
+                    .unwrap_or_else(|| format!("{}-asg", stack_name)),
+                stack_name,
+            },
+            default_server,
+            servers_table: optional(values, "FACTORIO_SERVERS_TABLE")?
+                .unwrap_or_else(|| "factorio-servers".to_string()),
+            interactions_table: optional(values, "FACTORIO_INTERACTIONS_TABLE")?
+                .unwrap_or_else(|| "discord-interaction-tokens".to_string()),
+            saves_bucket: optional(values, "FACTORIO_SAVES_BUCKET")?,
+            saves_prefix: optional(values, "FACTORIO_SAVES_PREFIX")?
+                .map(|prefix| format!("{}/", prefix.trim_end_matches('/')))
+                .unwrap_or_else(|| "saves/".to_string()),
+            worker_function_name: optional(values, "FACTORIO_WORKER_FUNCTION")?
+                .unwrap_or_else(|| "factorio-discord-worker".to_string()),
+            command_policies: json(values, "FACTORIO_COMMAND_POLICIES")?.unwrap_or_default(),
+            instance_sizes: json(values, "FACTORIO_INSTANCE_SIZES")?
+                .unwrap_or_else(default_instance_sizes),
+            rcon: match optional(values, "FACTORIO_RCON_PASSWORD")? {
+                Some(password) => Some(RconSettings {
+                    port: parsed(values, "FACTORIO_RCON_PORT")?.unwrap_or(rcon::DEFAULT_PORT),
+                    password,
+                    allow_lua: parsed(values, "FACTORIO_RCON_ALLOW_LUA")?.unwrap_or(false),
+                }),
+                None => None,
+            },
+            stop_countdown: Duration::seconds(
+                parsed(values, "FACTORIO_STOP_COUNTDOWN_SECONDS")?.unwrap_or(60),
+            ),
+            ready_timeout: Duration::seconds(
+                parsed(values, "FACTORIO_READY_TIMEOUT_SECONDS")?.unwrap_or(300),
+            ),
+            idle_table: optional(values, "FACTORIO_IDLE_TABLE")?
+                .unwrap_or_else(|| "factorio-idle-servers".to_string()),
+            sessions_table: optional(values, "FACTORIO_SESSIONS_TABLE")?
+                .unwrap_or_else(|| "factorio-sessions".to_string()),
+            interruptions_table: optional(values, "FACTORIO_INTERRUPTIONS_TABLE")?
+                .unwrap_or_else(|| "factorio-spot-interruptions".to_string()),
+            idle_shutdown: Duration::minutes(
+                parsed(values, "FACTORIO_IDLE_SHUTDOWN_MINUTES")?.unwrap_or(30),
+            ),
+            budget_table: optional(values, "FACTORIO_BUDGET_TABLE")?
+                .unwrap_or_else(|| "factorio-budget".to_string()),
+            budget_thresholds: json(values, "FACTORIO_BUDGET_THRESHOLDS")?
+                .unwrap_or_else(|| vec![50, 80, 100]),
+            budget_admins: json(values, "FACTORIO_BUDGET_ADMINS")?.unwrap_or_default(),
+            discord_app_id: required(values, "DISCORD_APP_ID")?,
+            discord_api_base: optional(values, "DISCORD_API_BASE")?
+                .unwrap_or_else(|| DEFAULT_API_BASE.to_string()),
+            discord_public_key: parse_public_key(&required(values, "DISCORD_PUBLIC_KEY")?)?,
+            discord_notify_webhook: optional(values, "DISCORD_NOTIFY_WEBHOOK")?,
+        };
+
+        if config.instance_sizes.is_empty() {
+            return Err(ConfigError::Invalid {
+                key: "FACTORIO_INSTANCE_SIZES",
+                reason: "must list at least one size".to_string(),
+            });
+        }
+
+        if config.stop_countdown.is_negative() {
+            return Err(ConfigError::Invalid {
+                key: "FACTORIO_STOP_COUNTDOWN_SECONDS",
+                reason: "must not be negative".to_string(),
+            });
+        }
+
+        if config.ready_timeout <= Duration::ZERO {
+            return Err(ConfigError::Invalid {
+                key: "FACTORIO_READY_TIMEOUT_SECONDS",
+                reason: "must be positive".to_string(),
+            });
+        }
+
+        if config.idle_shutdown <= Duration::ZERO {
+            return Err(ConfigError::Invalid {
+                key: "FACTORIO_IDLE_SHUTDOWN_MINUTES",
+                reason: "must be positive".to_string(),
+            });
+        }
+
+        if config.budget_thresholds.contains(&0) {
+            return Err(ConfigError::Invalid {
+                key: "FACTORIO_BUDGET_THRESHOLDS",
+                reason: "must be positive percentages".to_string(),
+            });
+        }
+        config.budget_thresholds.sort_unstable();
+        config.budget_thresholds.dedup();
+
+        if !config.discord_app_id.chars().all(|c| c.is_ascii_digit()) {
+            return Err(ConfigError::Invalid {
+                key: "DISCORD_APP_ID",
+                reason: "must be a numeric snowflake".to_string(),
+            });
+        }
+
+        info!(config.default_server, "loaded configuration");
+        Ok(config)
+    }
+}
+
+fn default_instance_sizes() -> BTreeMap<String, InstanceSize> {
+    [
+        ("small", "m5.large", "0.05"),
+        ("medium", "m5.xlarge", "0.10"),
+        ("large", "c5.2xlarge", "0.20"),
+    ]
+    .into_iter()
+    .map(|(name, instance_type, spot_price)| {
+        (
+            name.to_string(),
+            InstanceSize {
+                instance_type: instance_type.to_string(),
+                spot_price: spot_price.to_string(),
+            },
+        )
+    })
+    .collect()
+}
+
+fn optional(
+    values: &HashMap<String, String>,
+    key: &'static str,
+) -> Result<Option<String>, ConfigError> {
+    match values.get(key).map(|value| value.trim()) {
+        None => Ok(None),
+        Some("") => Err(ConfigError::Invalid {
+            key,
+            reason: "must not be empty".to_string(),
+        }),
+        Some(value) => Ok(Some(value.to_string())),
+    }
+}
+
+fn required(values: &HashMap<String, String>, key: &'static str) -> Result<String, ConfigError> {
+    optional(values, key)?.ok_or(ConfigError::Missing(key))
+}
+
+fn parsed<T>(values: &HashMap<String, String>, key: &'static str) -> Result<Option<T>, ConfigError>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    optional(values, key)?
+        .map(|value| {
+            value.parse().map_err(|err: T::Err| ConfigError::Invalid {
+                key,
+                reason: err.to_string(),
+            })
+        })
+        .transpose()
+}
+
+fn json<T: DeserializeOwned>(
+    values: &HashMap<String, String>,
+    key: &'static str,
+) -> Result<Option<T>, ConfigError> {
+    optional(values, key)?
+        .map(|value| {
+            serde_json::from_str(&value).map_err(|err| ConfigError::Invalid {
+                key,
+                reason: err.to_string(),
+            })
+        })
+        .transpose()
+}
+
+fn parse_public_key(hex_key: &str) -> Result<VerifyingKey, ConfigError> {
+    let invalid = |reason: String| ConfigError::Invalid {
+        key: "DISCORD_PUBLIC_KEY",
+        reason,
+    };
+
+    let mut key_bytes: [u8; PUBLIC_KEY_LENGTH] = [0; PUBLIC_KEY_LENGTH];
+    hex::decode_to_slice(hex_key, &mut key_bytes).map_err(|err| invalid(err.to_string()))?;
+    VerifyingKey::from_bytes(&key_bytes).map_err(|err| invalid(err.to_string()))
+}
+
+async fn load_ssm_parameters(
+    sdk_config: &aws_config::SdkConfig,
+    prefix: &str,
+) -> Result<HashMap<String, String>, ConfigError> {
+    info!(prefix, "loading parameters from SSM");
+    let client = aws_sdk_ssm::Client::new(sdk_config);
+
+    let mut values = HashMap::new();
+    let mut pages = client
+        .get_parameters_by_path()
+        .path(prefix)
+        .recursive(true)
+        .with_decryption(true)
+        .into_paginator()
+        .send();
+
+    while let Some(page) = pages.next().await {
+        let page = page.map_err(|err| ConfigError::Ssm(Box::new(err)))?;
+        for parameter in page.parameters() {
+            if let (Some(name), Some(value)) = (parameter.name(), parameter.value()) {
+                let key = name.rsplit('/').next().unwrap_or(name);
+                values.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+    Ok(values)
+}
@@ -0,0 +1,207 @@
+//! What the server's spot instances cost, from the EC2 spot price history.
+
+use time::{Duration, OffsetDateTime, Time};
+use tracing::{info, warn};
+
+use crate::{
+    aws_client::{compute::ServerAccessor, ddb::DynamoDBAccessor, ServerInfo},
+    error::Result,
+    model::domain::{InstanceCost, ServerDefinition, Session, SpotPrice},
+};
+
+/// A server's sessions since the start of the month, and what they cost. A
+/// session begun last month is included, counted from the start of the month.
+#[derive(Debug, Clone)]
+pub struct MonthToDate {
+    /// The start of the month.
+    pub since: OffsetDateTime,
+    pub sessions: Vec<Session>,
+    /// Estimate for the session that is still running, which is only settled
+    /// once it stops.
+    pub running_cost: Option<f64>,
+}
+
+impl MonthToDate {
+    pub fn total(&self) -> f64 {
+        let settled: f64 = self
+            .sessions
+            .iter()
+            .filter_map(|session| session.cost_since(self.since))
+            .sum();
+        settled + self.running_cost.unwrap_or_default()
+    }
+}
+
+/// Midnight UTC on the first day of `now`'s month.
+pub fn month_start(now: OffsetDateTime) -> Result<OffsetDateTime> {
+    Ok(now
+        .to_offset(time::UtcOffset::UTC)
+        .replace_day(1)?
+        .replace_time(Time::MIDNIGHT))
+}
+
+/// What the server cost since the start of the month, up until `now`.
+pub async fn month_to_date(
+    ddb: &DynamoDBAccessor,
+    server_accessor: &ServerAccessor,
+    server: &ServerDefinition,
+    now: OffsetDateTime,
+) -> Result<MonthToDate> {
+    let since = month_start(now)?;
+    let mut sessions = ddb.list_sessions_since(&server.name, since).await?;
+    // Sessions are keyed by their start, which misses one begun last month
+    if let Some(earlier) = ddb.get_session_before(&server.name, since).await? {
+        if earlier
+            .stopped_at
+            .is_none_or(|stopped_at| stopped_at > since)
+        {
+            sessions.push(earlier);
+        }
+    }
+
+    let running_cost = match sessions
+        .iter()
+        .find(|session| session.stopped_at.is_none() && session.cost.is_none())
+    {
+        Some(session) => {
+            // Count from the instance's launch when known, like a settled session
+            let launched_at = server_accessor
+                .get_instance_status(server)
+                .await?
+                .and_then(|instance| instance.launched_at)
+                .unwrap_or_else(|| current_instance_since(session));
+            let interrupted: f64 = session
+                .interrupted
+                .iter()
+                .map(|instance| instance.cost_since(since))
+                .sum();
+            instance_cost(server_accessor, server, launched_at.max(since), now)
+                .await?
+                .map(|cost| cost + interrupted)
+        }
+        None => None,
+    };
+
+    Ok(MonthToDate {
+        since,
+        sessions,
+        running_cost,
+    })
+}
+
+/// The cost of running between `from` and `to`, given the spot prices in
+/// effect over that window, sorted oldest first.
+///
+/// Each price applies until the next one. If the history starts after `from`,
+/// its first price is assumed for the gap.
+pub fn spot_cost(prices: &[SpotPrice], from: OffsetDateTime, to: OffsetDateTime) -> f64 {
+    if to <= from || prices.is_empty() {
+        return 0.0;
+    }
+
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, price)| {
+            let start = if i == 0 {
+                from
+            } else {
+                price.timestamp.max(from)
+            };
+            let end = prices.get(i + 1).map_or(to, |next| next.timestamp.min(to));
+            hours(end - start).max(0.0) * price.price
+        })
+        .sum()
+}
+
+fn hours(duration: Duration) -> f64 {
+    duration.as_seconds_f64() / 3600.0
+}
+
+/// What the server's current instance cost from `from` until `to`. `None` when
+/// no instance is running.
+pub async fn instance_cost(
+    server_accessor: &ServerAccessor,
+    server: &ServerDefinition,
+    from: OffsetDateTime,
+    to: OffsetDateTime,
+) -> Result<Option<f64>> {
+    let Some(placement) = server_accessor.get_instance_placement(server).await? else {
+        return Ok(None);
+    };
+    let prices = server_accessor
+        .get_spot_price_history(&placement, from, to)
+        .await?;
+
+    let cost = spot_cost(&prices, from, to);
+    info!(?placement, cost, "computed instance cost");
+    Ok(Some(cost))
+}
+
+/// When the session's current instance started, if its launch time is unknown.
+fn current_instance_since(session: &Session) -> OffsetDateTime {
+    session
+        .interrupted
+        .last()
+        .map_or(session.started_at, |instance| instance.interrupted_at)
+}
+
+/// Records what the server's instance cost since `launched_at` on its open
+/// session, as a spot interruption is about to reclaim it. The instance that
+/// replaces it is settled with the session.
+pub async fn settle_interrupted_instance(
+    ddb: &DynamoDBAccessor,
+    server_accessor: &ServerAccessor,
+    server: &ServerDefinition,
+    launched_at: Option<OffsetDateTime>,
+) -> Result<()> {
+    let Some(mut session) = ddb.get_open_session(&server.name).await? else {
+        return Ok(());
+    };
+
+    let launched_at = launched_at.unwrap_or_else(|| current_instance_since(&session));
+    let now = OffsetDateTime::now_utc();
+    let Some(cost) = instance_cost(server_accessor, server, launched_at, now).await? else {
+        return Ok(());
+    };
+    session.interrupted.push(InstanceCost {
+        launched_at,
+        interrupted_at: now,
+        cost,
+    });
+    ddb.put_session(&session).await
+}
+
+/// Settles the server's open session as it is being stopped: records who
+/// stopped it, and what its instances cost, the current one since
+/// `launched_at`. The instance must still be running, as its placement is
+/// needed for the price.
+pub async fn settle_session(
+    ddb: &DynamoDBAccessor,
+    server_accessor: &ServerAccessor,
+    server: &ServerDefinition,
+    stopped_by: &str,
+    launched_at: Option<OffsetDateTime>,
+) -> Result<()> {
+    let Some(mut session) = ddb.get_open_session(&server.name).await? else {
+        return Ok(());
+    };
+
+    let from = launched_at.unwrap_or_else(|| current_instance_since(&session));
+    let interrupted: f64 = session
+        .interrupted
+        .iter()
+        .map(|instance| instance.cost)
+        .sum();
+    session.stopped_by = Some(stopped_by.to_string());
+    // A missing price should not keep the server running
+    session.cost =
+        match instance_cost(server_accessor, server, from, OffsetDateTime::now_utc()).await {
+            Ok(cost) => cost.map(|cost| cost + interrupted),
+            Err(cost_err) => {
+                warn!(?cost_err, server.name, "could not compute the session cost");
+                None
+            }
+        };
+    ddb.put_session(&session).await
+}
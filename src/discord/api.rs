@@ -0,0 +1,65 @@
+use tracing::info;
+
+use super::command::ApplicationCommand;
+use crate::error::Result;
+
+pub const DEFAULT_API_BASE: &str = "https://discord.com/api/v10";
+
+/// Where application commands are registered.
+#[derive(Debug, Clone)]
+pub enum CommandScope {
+    /// Available in every guild the bot is in. Updates can take up to an hour to show up.
+    Global,
+    /// Only available in a single guild, but updated instantly.
+    Guild(String),
+}
+
+/// Client for the parts of the Discord REST API that need the bot token.
+pub struct DiscordApi {
+    client: reqwest::Client,
+    base_url: String,
+    app_id: String,
+    bot_token: String,
+}
+
+impl DiscordApi {
+    pub fn new(base_url: &str, app_id: &str, bot_token: &str) -> Self {
+        DiscordApi {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            app_id: app_id.to_string(),
+            bot_token: bot_token.to_string(),
+        }
+    }
+
+    /// Overwrites the bot's application commands in the given scope.
+    pub async fn register_commands(
+        &self,
+        scope: &CommandScope,
+        commands: &[ApplicationCommand],
+    ) -> Result<()> {
+        let url = match scope {
+            CommandScope::Global => {
+                format!("{}/applications/{}/commands", self.base_url, self.app_id)
+            }
+            CommandScope::Guild(guild_id) => format!(
+                "{}/applications/{}/guilds/{}/commands",
+                self.base_url, self.app_id, guild_id
+            ),
+        };
+        info!(url, "Registering commands");
+
+        self.client
+            .put(url)
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("Bot {}", self.bot_token),
+            )
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(commands)?)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use ed25519_dalek::{Signature, Verifier, VerifyingKey, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey, SIGNATURE_LENGTH};
 
 use super::{DiscordAuthError, SignedRequest, VerifyDiscordReq};
 
@@ -7,16 +7,8 @@ pub struct DiscordAuthenticator {
 }
 
 impl DiscordAuthenticator {
-    pub fn new() -> Self {
-        DiscordAuthenticator {
-            public_key: get_verifying_key(),
-        }
-    }
-}
-
-impl Default for DiscordAuthenticator {
-    fn default() -> Self {
-        DiscordAuthenticator::new()
+    pub fn new(public_key: VerifyingKey) -> Self {
+        DiscordAuthenticator { public_key }
     }
 }
 
@@ -44,14 +36,3 @@ impl VerifyDiscordReq for DiscordAuthenticator {
             .verify(format!("{}{}", timestamp, event.body).as_bytes(), &sig)?)
     }
 }
-
-fn get_verifying_key() -> VerifyingKey {
-    let mut key_bytes: [u8; PUBLIC_KEY_LENGTH] = [0; PUBLIC_KEY_LENGTH];
-    hex::decode_to_slice(
-        "5de3bcb92187f4dcc23ac1b2d2276caa0ccac57ab592f76ee57c4d7f0e692252",
-        &mut key_bytes,
-    )
-    .expect("PK decoding failed");
-
-    VerifyingKey::from_bytes(&key_bytes).expect("Invalid VerifyingKey")
-}
@@ -0,0 +1,75 @@
+use serde::Serialize;
+
+use super::{model::CommandOptionType, response::CommandOptionChoice};
+
+/// A slash command as registered with Discord.
+///
+/// See https://discord.com/developers/docs/interactions/application-commands#application-command-object
+#[derive(Debug, Clone, Serialize)]
+pub struct ApplicationCommand {
+    pub name: String,
+    pub description: String,
+    pub options: Vec<CommandOptionDefinition>,
+}
+
+/// The definition of an option or subcommand of a slash command.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandOptionDefinition {
+    #[serde(rename = "type")]
+    pub kind: CommandOptionType,
+    pub name: String,
+    pub description: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub autocomplete: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<CommandOptionChoice>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<CommandOptionDefinition>,
+}
+
+impl CommandOptionDefinition {
+    pub fn new(kind: CommandOptionType, name: &str, description: &str) -> Self {
+        CommandOptionDefinition {
+            kind,
+            name: name.to_string(),
+            description: description.to_string(),
+            required: false,
+            autocomplete: false,
+            choices: vec![],
+            options: vec![],
+        }
+    }
+
+    pub fn string(name: &str, description: &str) -> Self {
+        CommandOptionDefinition::new(CommandOptionType::String, name, description)
+    }
+
+    pub fn integer(name: &str, description: &str) -> Self {
+        CommandOptionDefinition::new(CommandOptionType::Integer, name, description)
+    }
+
+    pub fn number(name: &str, description: &str) -> Self {
+        CommandOptionDefinition::new(CommandOptionType::Number, name, description)
+    }
+
+    pub fn boolean(name: &str, description: &str) -> Self {
+        CommandOptionDefinition::new(CommandOptionType::Boolean, name, description)
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub fn autocomplete(mut self) -> Self {
+        self.autocomplete = true;
+        self
+    }
+
+    pub fn choice(mut self, choice: CommandOptionChoice) -> Self {
+        self.choices.push(choice);
+        self
+    }
+}
@@ -1,10 +1,19 @@
+pub mod api;
 pub mod auth;
+pub mod command;
+pub mod model;
+pub mod present;
+pub mod response;
+pub mod webhook;
 
 use lambda_http::http::{HeaderMap, HeaderValue};
 use thiserror::Error;
 
+/// How long after a command its interaction token can edit the reply.
+pub const INTERACTION_TOKEN_LIFETIME: time::Duration = time::Duration::minutes(15);
+
 pub struct SignedRequest<'a> {
-    pub body: &'a String,
+    pub body: &'a str,
     pub headers: &'a HeaderMap<HeaderValue>,
 }
 
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+/// The kind of interaction Discord is sending us.
+///
+/// See https://discord.com/developers/docs/interactions/receiving-and-responding#interaction-object-interaction-type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum InteractionType {
+    Ping = 1,
+    ApplicationCommand = 2,
+    MessageComponent = 3,
+    ApplicationCommandAutocomplete = 4,
+    ModalSubmit = 5,
+}
+
+/// An incoming interaction, limited to the fields the bot makes use of.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub id: String,
+    pub application_id: String,
+    #[serde(rename = "type")]
+    pub kind: InteractionType,
+    pub token: String,
+    #[serde(default)]
+    pub data: Option<ApplicationCommandData>,
+    #[serde(default)]
+    pub guild_id: Option<String>,
+    #[serde(default)]
+    pub guild: Option<PartialGuild>,
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    /// Present when the interaction was invoked in a guild.
+    #[serde(default)]
+    pub member: Option<GuildMember>,
+    /// Present when the interaction was invoked in a DM.
+    #[serde(default)]
+    pub user: Option<User>,
+}
+
+impl Interaction {
+    /// The user that triggered the interaction, whether in a guild or a DM.
+    pub fn invoker(&self) -> Option<&User> {
+        self.member
+            .as_ref()
+            .and_then(|member| member.user.as_ref())
+            .or(self.user.as_ref())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApplicationCommandData {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub options: Vec<CommandOption>,
+}
+
+impl ApplicationCommandData {
+    /// The subcommand that was invoked, e.g. `start` in `/factorio start`.
+    pub fn subcommand(&self) -> Option<&CommandOption> {
+        self.options
+            .iter()
+            .find(|option| option.kind == CommandOptionType::SubCommand)
+    }
+}
+
+/// See https://discord.com/developers/docs/interactions/application-commands#application-command-object-application-command-option-type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum CommandOptionType {
+    SubCommand = 1,
+    SubCommandGroup = 2,
+    String = 3,
+    Integer = 4,
+    Boolean = 5,
+    User = 6,
+    Channel = 7,
+    Role = 8,
+    Mentionable = 9,
+    Number = 10,
+    Attachment = 11,
+}
+
+/// An option as received in an interaction. Subcommands carry their own
+/// options, while value options carry a `value`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandOption {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: CommandOptionType,
+    #[serde(default)]
+    pub value: Option<OptionValue>,
+    #[serde(default)]
+    pub options: Vec<CommandOption>,
+    /// Set on the option the user is currently typing in during autocomplete.
+    #[serde(default)]
+    pub focused: bool,
+}
+
+impl CommandOption {
+    pub fn option(&self, name: &str) -> Option<&CommandOption> {
+        self.options.iter().find(|option| option.name == name)
+    }
+
+    /// The option the user is currently typing in, during autocomplete.
+    pub fn focused(&self) -> Option<&CommandOption> {
+        self.options.iter().find(|option| option.focused)
+    }
+
+    /// Returns the value of a string option, if it was provided.
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.option(name)?.value.as_ref()? {
+            OptionValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of an integer option, if it was provided.
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        match self.option(name)?.value.as_ref()? {
+            OptionValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a number option, if it was provided. Whole numbers
+    /// are sent without a fraction, so they read as integers.
+    pub fn get_number(&self, name: &str) -> Option<f64> {
+        match self.option(name)?.value.as_ref()? {
+            OptionValue::Number(value) => Some(*value),
+            OptionValue::Integer(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a boolean option, if it was provided.
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.option(name)?.value.as_ref()? {
+            OptionValue::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OptionValue {
+    String(String),
+    Integer(i64),
+    Number(f64),
+    Boolean(bool),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuildMember {
+    #[serde(default)]
+    pub user: Option<User>,
+    #[serde(default)]
+    pub nick: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub id: String,
+    pub username: String,
+    #[serde(default)]
+    pub global_name: Option<String>,
+}
+
+impl User {
+    /// The name shown in Discord, falling back to the unique username.
+    pub fn display_name(&self) -> &str {
+        self.global_name.as_deref().unwrap_or(&self.username)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartialGuild {
+    pub id: String,
+    #[serde(default)]
+    pub locale: Option<String>,
+}
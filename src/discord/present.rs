@@ -0,0 +1,552 @@
+//! Turns domain results into the messages shown in Discord.
+
+use time::{Duration, OffsetDateTime};
+
+use crate::cost::MonthToDate;
+use crate::error::Error;
+use crate::model::domain::{
+    BudgetUsage, PlayersStatus, ServerIpStatus, ServerLifecycle, ServerStatus, Session,
+    StackFailure, StopProgress, UpdateOutcome,
+};
+
+use super::response::{color, Embed, MessageData, MessageFlags};
+
+fn outcome_title(outcome: UpdateOutcome, success: &'static str) -> &'static str {
+    match outcome {
+        UpdateOutcome::Updating => success,
+        UpdateOutcome::AlreadyInState => "Server is already in the desired state.",
+        UpdateOutcome::UpdateInProgress => "Server is currently being updated",
+    }
+}
+
+pub fn start_server(
+    outcome: UpdateOutcome,
+    server: &str,
+    mount_dir: &str,
+    size: Option<&str>,
+) -> MessageData {
+    let mut embed = Embed::factorio(outcome_title(outcome, "Starting the server!"), color::INFO);
+    if outcome == UpdateOutcome::Updating {
+        embed = embed.description(format!(
+            "Using the `{}` save on `{}`. This message will update when the server is ready to join.",
+            mount_dir, server
+        ));
+        if let Some(size) = size {
+            embed = embed.field("Size", format!("`{}`", size), true);
+        }
+    } else if outcome == UpdateOutcome::UpdateInProgress {
+        embed = embed.description(format!(
+            "`{}` is already being updated. If it is starting, this message will update once it is ready to join.",
+            server
+        ));
+    }
+    MessageData::embed(embed)
+}
+
+/// Replaces the message of a start that was made while the server was
+/// already starting for someone else.
+pub fn already_starting(server: &str, ip: &str) -> MessageData {
+    MessageData::embed(
+        Embed::factorio("Starting the server!", color::INFO)
+            .description(format!(
+                "`{}` was already starting when you asked, see above for when it is ready.",
+                server
+            ))
+            .field("Server IP", format!("`{}`", ip), true),
+    )
+}
+
+pub fn stop_server(outcome: UpdateOutcome, server: &str, saved: bool) -> MessageData {
+    let mut embed = Embed::factorio(
+        outcome_title(outcome, "Stopping the server!"),
+        color::DANGER,
+    );
+    if outcome == UpdateOutcome::Updating {
+        embed = embed.description(if saved {
+            format!(
+                "The game was saved, shutting down `{}`. This message will update when the server has stopped.",
+                server
+            )
+        } else {
+            format!(
+                "Shutting down `{}`. This message will update when the server has stopped.",
+                server
+            )
+        });
+    }
+    MessageData::embed(embed)
+}
+
+/// Shown when a command would move the server into a state it can not reach from here.
+pub fn lifecycle_conflict(server: &str, lifecycle: ServerLifecycle, action: &str) -> MessageData {
+    MessageData::content(format!(
+        "Can not {} `{}` while it is {}.",
+        action, server, lifecycle
+    ))
+}
+
+pub fn stop_progress(server: &str, progress: StopProgress) -> MessageData {
+    let description = match progress {
+        StopProgress::Warning { remaining } => format!(
+            "Warned players on `{}`, saving in {} seconds.",
+            server,
+            remaining.whole_seconds()
+        ),
+        StopProgress::Saving => format!("Saving the game on `{}`...", server),
+    };
+    MessageData::embed(
+        Embed::factorio("Stopping the server!", color::DANGER).description(description),
+    )
+}
+
+/// Reply to a stop of a running server while RCON is disabled.
+pub fn stop_without_save(outcome: UpdateOutcome, server: &str) -> MessageData {
+    let mut message = stop_server(outcome, server, false);
+    message.content =
+        "RCON is disabled, so the game could not be saved before stopping.".to_string();
+    message
+}
+
+/// Reply to a stop that was called off because the game could not be saved.
+pub fn save_failed(server: &str) -> MessageData {
+    MessageData::content(format!(
+        "Could not save `{}` over RCON, so it was left running. Try again, or use `force: True` to stop it without saving.",
+        server
+    ))
+}
+
+pub fn server_ip(server: &str, status: &ServerIpStatus) -> MessageData {
+    MessageData::content(match status {
+        ServerIpStatus::NotRunning => format!("`{}` is not running.", server),
+        ServerIpStatus::InstanceNotReady(state) => {
+            format!("`{}` instance is in the {} state", server, state)
+        }
+        ServerIpStatus::Starting { ip } => format!(
+            "`{}` IP will be: `{}`. However, factorio has not started running yet.",
+            server, ip
+        ),
+        ServerIpStatus::Running { ip } => {
+            format!("`{}` is up and running at IP: `{}`!", server, ip)
+        }
+    })
+}
+
+/// Shown while the instance is up but factorio is still loading.
+pub fn server_waiting(server: &str, ip: &str) -> MessageData {
+    MessageData::embed(
+        Embed::factorio("Starting the server!", color::INFO)
+            .description(format!(
+                "The instance for `{}` is up, waiting for Factorio to finish loading.",
+                server
+            ))
+            .field("Server IP", format!("`{}`", ip), true),
+    )
+}
+
+/// Shown when factorio did not come up in time, or no instance came into
+/// service at all.
+pub fn server_not_ready(server: &str, ip: Option<&str>, waited: Duration) -> MessageData {
+    let embed = Embed::factorio("The server is taking too long to start", color::DANGER);
+    MessageData::embed(match ip {
+        Some(ip) => embed
+            .description(format!(
+                "The instance for `{}` is up, but Factorio did not answer within {}. Use `/factorio status` to check on it.",
+                server,
+                format_duration(waited)
+            ))
+            .field("Server IP", format!("`{}`", ip), true),
+        None => embed.description(format!(
+            "No instance for `{}` came into service within {}. Use `/factorio status` to check on it.",
+            server,
+            format_duration(waited)
+        )),
+    })
+}
+
+pub fn server_ready(server: &str, ip: &str, launch_time: Duration) -> MessageData {
+    MessageData::embed(
+        Embed::factorio("Starting the server!", color::SUCCESS)
+            .description(format!(
+                "Factorio has successfully launched `{}` in {}m {}s.",
+                server,
+                launch_time.whole_minutes(),
+                launch_time.whole_seconds() % 60
+            ))
+            .field("Server IP", format!("`{}`", ip), true),
+    )
+}
+
+/// Replaces the stop message once the stack update has completed.
+pub fn server_stopped(server: &str, uptime: Option<Duration>, cost: Option<f64>) -> MessageData {
+    let title = match uptime {
+        Some(uptime) => format!("Server stopped after {}", format_duration(uptime)),
+        None => "Server stopped".to_string(),
+    };
+    let mut embed =
+        Embed::factorio(title, color::DANGER).description(format!("`{}` is now stopped.", server));
+    if let Some(cost) = cost {
+        embed = embed.field("Cost", format_cost(cost), true);
+    }
+    MessageData::embed(embed)
+}
+
+/// Formats US dollars, e.g. `$1.27`.
+pub fn format_cost(cost: f64) -> String {
+    format!("${:.2}", cost)
+}
+
+/// Month-to-date spending on a server.
+pub fn cost_summary(server: &str, month: &str, cost: &MonthToDate) -> MessageData {
+    let played: Duration = cost
+        .sessions
+        .iter()
+        .filter_map(|session| session.duration())
+        .sum();
+    let mut embed = Embed::factorio(format!("Cost of `{}` in {}", server, month), color::INFO)
+        .field("Total", format_cost(cost.total()), true)
+        .field("Sessions", cost.sessions.len().to_string(), true)
+        .field("Played", format_duration(played), true);
+    if let Some(running_cost) = cost.running_cost {
+        embed = embed.description(format!(
+            "Includes {} for the session that is still running.",
+            format_cost(running_cost)
+        ));
+    }
+    MessageData::embed(embed)
+}
+
+/// Reply to `/factorio budget`, when no amount was given.
+pub fn budget_status(usage: Option<&BudgetUsage>) -> MessageData {
+    let Some(usage) = usage else {
+        return MessageData::content("No monthly budget is set.");
+    };
+    let color = if usage.is_exhausted() {
+        color::DANGER
+    } else {
+        color::INFO
+    };
+    MessageData::embed(
+        Embed::factorio(
+            format!(
+                "{:.0}% of the monthly budget is spent",
+                usage.percent_used()
+            ),
+            color,
+        )
+        .field("Spent", format_cost(usage.spent), true)
+        .field("Budget", format_cost(usage.amount), true),
+    )
+}
+
+pub fn budget_set(amount: f64) -> MessageData {
+    MessageData::content(format!(
+        "The monthly budget is now {}.",
+        format_cost(amount)
+    ))
+}
+
+pub fn invalid_budget(amount: f64) -> MessageData {
+    MessageData::content(format!(
+        "The budget must be a positive amount of US dollars, not `{}`.",
+        amount
+    ))
+}
+
+/// Reply to a start that was refused because the budget is spent.
+pub fn budget_exhausted(usage: &BudgetUsage) -> MessageData {
+    MessageData::embed(
+        Embed::factorio("The monthly budget is spent", color::DANGER).description(format!(
+            "{} of the {} budget was spent this month, so the server was not started. An admin can start it anyway with `override: True`.",
+            format_cost(usage.spent),
+            format_cost(usage.amount)
+        )),
+    )
+}
+
+pub fn budget_override_denied() -> MessageData {
+    MessageData::content(
+        "The monthly budget is spent, and only budget admins may start the server anyway.",
+    )
+}
+
+/// Notice posted once a month when spending crosses one of the thresholds.
+pub fn budget_warning(percent: u32, usage: &BudgetUsage) -> MessageData {
+    let color = if usage.is_exhausted() {
+        color::DANGER
+    } else {
+        color::WARNING
+    };
+    MessageData::embed(
+        Embed::factorio(
+            format!("{}% of the monthly budget is spent", percent),
+            color,
+        )
+        .description(format!(
+            "The servers cost {} of the {} budget so far this month.",
+            format_cost(usage.spent),
+            format_cost(usage.amount)
+        )),
+    )
+}
+
+/// Shown in place of the start or stop message when the stack update failed.
+pub fn server_failed(
+    server: &str,
+    action: &str,
+    stack_status: &str,
+    failure: Option<&StackFailure>,
+) -> MessageData {
+    let title = format!("The server failed to {}!", action);
+    let mut embed = Embed::factorio(title, color::DANGER).description(format!(
+        "The stack update of `{}` ended in `{}`.",
+        server, stack_status
+    ));
+    if let Some(failure) = failure {
+        embed = embed.field(
+            format!("`{}` failed", failure.resource),
+            failure.reason.clone(),
+            false,
+        );
+    }
+    MessageData::embed(embed)
+}
+
+pub fn not_authorized(command: &str) -> MessageData {
+    MessageData::content(format!(
+        "You are not allowed to use `/factorio {}`.",
+        command
+    ))
+}
+
+pub fn players(server: &str, status: &PlayersStatus) -> MessageData {
+    match status {
+        PlayersStatus::NotRunning => MessageData::content(format!("`{}` is not running.", server)),
+        PlayersStatus::RconDisabled => {
+            MessageData::content("RCON is disabled, so players can not be listed.")
+        }
+        PlayersStatus::Unreachable => MessageData::content(format!(
+            "Could not reach `{}` over RCON. Factorio may still be starting.",
+            server
+        )),
+        PlayersStatus::Online { total, online } => {
+            let title = format!("{} of {} players online", online.len(), total);
+            let mut embed = Embed::factorio(title, color::INFO);
+            if !online.is_empty() {
+                embed = embed.description(
+                    online
+                        .iter()
+                        .map(|player| match player.online_time {
+                            Some(online_time) => format!(
+                                "**{}** has played {} in this save",
+                                player.name,
+                                format_duration(online_time)
+                            ),
+                            None => format!("**{}**", player.name),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+            }
+            MessageData::embed(embed)
+        }
+    }
+}
+
+/// Formats a duration as e.g. `3h 12m`, or `5m` when shorter than an hour.
+pub fn format_duration(duration: Duration) -> String {
+    let hours = duration.whole_hours();
+    let minutes = duration.whole_minutes() % 60;
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+pub fn unknown_save(save: &str) -> MessageData {
+    MessageData::content(format!("No save named `{}` exists.", save))
+}
+
+pub fn unknown_size<'a>(
+    size: &str,
+    available: impl IntoIterator<Item = &'a String>,
+) -> MessageData {
+    let available: Vec<String> = available
+        .into_iter()
+        .map(|name| format!("`{}`", name))
+        .collect();
+    MessageData::content(format!(
+        "No size named `{}` exists. Pick one of {}.",
+        size,
+        available.join(", ")
+    ))
+}
+
+pub fn idle_shutdown(server: &str, idle_for: Duration) -> MessageData {
+    MessageData::embed(
+        Embed::factorio("Stopping the server!", color::DANGER).description(format!(
+            "`{}` had no players online for {} minutes. The game was saved and the server is shutting down.",
+            server,
+            idle_for.whole_minutes()
+        )),
+    )
+}
+
+pub fn spot_interruption(server: &str, saved: bool) -> MessageData {
+    let saved = if saved {
+        "The game was saved."
+    } else {
+        "The game could not be saved, progress since the last autosave may be lost."
+    };
+    MessageData::embed(
+        Embed::factorio("Spot instance interrupted!", color::DANGER).description(format!(
+            "AWS is reclaiming the instance of `{}` in two minutes. {} A new instance will be started with the same save.",
+            server, saved
+        )),
+    )
+}
+
+pub fn unknown_server(server: &str) -> MessageData {
+    MessageData::content(format!("No server named `{}` is registered.", server))
+}
+
+pub fn status(server: &str, status: &ServerStatus, now: OffsetDateTime) -> MessageData {
+    let stack = &status.stack;
+    let lifecycle = status.lifecycle();
+    let embed_color = match lifecycle {
+        ServerLifecycle::Running => color::SUCCESS,
+        ServerLifecycle::Starting | ServerLifecycle::Stopping => color::INFO,
+        _ => color::DANGER,
+    };
+    let or_unknown = |value: &Option<String>| value.as_deref().unwrap_or("unknown").to_string();
+
+    let mut embed = Embed::factorio(format!("`{}` is {}", server, lifecycle), embed_color)
+        .field("Stack", format!("`{}`", stack.status), true)
+        .field("State", or_unknown(&stack.server_state), true)
+        .field("Save", or_unknown(&stack.save), true)
+        .field("Instance type", or_unknown(&stack.instance_type), true)
+        .field("Spot price", or_unknown(&stack.spot_price), true)
+        .field("Image tag", or_unknown(&stack.image_tag), true);
+
+    embed = match &status.instance {
+        Some(instance) => {
+            embed = embed
+                .field(
+                    "Instance",
+                    format!("`{}` {}", instance.instance_id, instance.lifecycle),
+                    true,
+                )
+                .field(
+                    "Server IP",
+                    instance
+                        .ip
+                        .as_ref()
+                        .map_or("none".to_string(), |ip| format!("`{}`", ip)),
+                    true,
+                );
+            match instance.launched_at {
+                Some(launched_at) => embed.field(
+                    "Uptime",
+                    format!(
+                        "{} (since <t:{}:t>)",
+                        format_duration(now - launched_at),
+                        launched_at.unix_timestamp()
+                    ),
+                    true,
+                ),
+                None => embed,
+            }
+        }
+        None => embed.field("Instance", "none", true),
+    };
+
+    embed = match &status.service {
+        Some(service) => embed
+            .field(
+                "ECS deployment",
+                format!(
+                    "{}, {}/{} running, {} pending",
+                    or_unknown(&service.rollout),
+                    service.running,
+                    service.desired,
+                    service.pending
+                ),
+                true,
+            )
+            .field("Task", or_unknown(&service.task), true),
+        None => embed.field("ECS deployment", "not found", true),
+    };
+    MessageData::embed(embed)
+}
+
+/// Explains a failed command to the user who ran it. The error ID matches the
+/// one logged with the error, so the two can be tied together.
+pub fn error(err: &Error, error_id: &str) -> MessageData {
+    let explanation = match err {
+        Error::Throttled(_) => "AWS is busy right now, please try again in a minute.".to_string(),
+        Error::Validation(message) => format!("AWS rejected the request: {}", message),
+        Error::NotFound(what) => format!("Could not find the {}.", what),
+        Error::Discord(_) => "Could not reach Discord, please try again.".to_string(),
+        Error::BadInput(message) => format!("The command could not be understood: {}", message),
+        Error::Rcon(_) => "Could not talk to the game over RCON.".to_string(),
+        Error::Config(_) | Error::Data(_) | Error::Aws(_) => {
+            "Something went wrong while running the command.".to_string()
+        }
+    };
+    MessageData::embed(
+        Embed::factorio("Something went wrong", color::DANGER)
+            .description(explanation)
+            .field("Error ID", format!("`{}`", error_id), false),
+    )
+    .with_flags(MessageFlags::EPHEMERAL)
+}
+
+pub fn history(server: &str, page: usize, sessions: &[Session], has_more: bool) -> MessageData {
+    let mut embed = Embed::factorio(format!("Sessions of `{}`", server), color::INFO);
+    if sessions.is_empty() {
+        return MessageData::embed(embed.description("No sessions were found."));
+    }
+
+    for session in sessions {
+        let mut details = vec![format!("Started by {}", session.started_by)];
+        if let Some(instance_type) = &session.instance_type {
+            details.push(format!("on `{}`", instance_type));
+        }
+        if let Some(ready_after) = session.ready_after {
+            details.push(format!("ready after {}", format_duration(ready_after)));
+        }
+        match (session.duration(), &session.stopped_by) {
+            (Some(duration), Some(stopped_by)) => details.push(format!(
+                "ran for {}, stopped by {}",
+                format_duration(duration),
+                stopped_by
+            )),
+            (Some(duration), None) => {
+                details.push(format!("ran for {}", format_duration(duration)))
+            }
+            (None, Some(stopped_by)) => details.push(format!("stopping, by {}", stopped_by)),
+            (None, None) => details.push("still running".to_string()),
+        }
+
+        embed = embed.field(
+            format!(
+                "<t:{}:f> `{}`",
+                session.started_at.unix_timestamp(),
+                session.save
+            ),
+            details.join(", "),
+            false,
+        );
+    }
+
+    let footer = if has_more {
+        format!(
+            "Page {}. Use `page: {}` for older sessions.",
+            page,
+            page + 1
+        )
+    } else {
+        format!("Page {}, no older sessions.", page)
+    };
+    MessageData::embed(embed.description(footer))
+}
@@ -0,0 +1,234 @@
+use std::ops::BitOr;
+
+use serde::Serialize;
+use serde_repr::Serialize_repr;
+
+/// Embed colors used across the bot's messages.
+pub mod color {
+    pub const INFO: u32 = 0x00FFFF;
+    pub const SUCCESS: u32 = 0x1de302;
+    pub const WARNING: u32 = 0xf5a623;
+    pub const DANGER: u32 = 0x930707;
+}
+
+const FACTORIO_THUMBNAIL: &str = "https://factorio.com/static/img/factorio-wheel.png";
+
+/// See https://discord.com/developers/docs/interactions/receiving-and-responding#interaction-response-object-interaction-callback-type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr)]
+#[repr(u8)]
+pub enum InteractionCallbackType {
+    Pong = 1,
+    ChannelMessageWithSource = 4,
+    DeferredChannelMessageWithSource = 5,
+    DeferredUpdateMessage = 6,
+    UpdateMessage = 7,
+    ApplicationCommandAutocompleteResult = 8,
+}
+
+/// The body returned to Discord in reply to an interaction.
+#[derive(Debug, Clone, Serialize)]
+pub struct InteractionResponse {
+    #[serde(rename = "type")]
+    pub kind: InteractionCallbackType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<InteractionCallbackData>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum InteractionCallbackData {
+    Message(MessageData),
+    Autocomplete { choices: Vec<CommandOptionChoice> },
+}
+
+impl InteractionResponse {
+    pub fn pong() -> Self {
+        InteractionResponse {
+            kind: InteractionCallbackType::Pong,
+            data: None,
+        }
+    }
+
+    /// Acknowledges the interaction, showing a loading state until the
+    /// original message is edited through the webhook API.
+    pub fn deferred() -> Self {
+        InteractionResponse {
+            kind: InteractionCallbackType::DeferredChannelMessageWithSource,
+            data: None,
+        }
+    }
+
+    pub fn message(data: MessageData) -> Self {
+        InteractionResponse {
+            kind: InteractionCallbackType::ChannelMessageWithSource,
+            data: Some(InteractionCallbackData::Message(data)),
+        }
+    }
+
+    /// Suggestions shown to the user while they type an autocomplete option.
+    pub fn autocomplete(choices: Vec<CommandOptionChoice>) -> Self {
+        InteractionResponse {
+            kind: InteractionCallbackType::ApplicationCommandAutocompleteResult,
+            data: Some(InteractionCallbackData::Autocomplete { choices }),
+        }
+    }
+}
+
+/// Message contents, used both for interaction responses and webhook edits.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MessageData {
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub tts: bool,
+    pub content: String,
+    pub embeds: Vec<Embed>,
+    pub allowed_mentions: AllowedMentions,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<MessageFlags>,
+}
+
+impl MessageData {
+    pub fn content(content: impl Into<String>) -> Self {
+        MessageData {
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn embed(embed: Embed) -> Self {
+        MessageData {
+            embeds: vec![embed],
+            ..Default::default()
+        }
+    }
+
+    pub fn with_flags(mut self, flags: MessageFlags) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+}
+
+/// Which mentions in the message are allowed to ping. Defaults to none.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AllowedMentions {
+    pub parse: Vec<String>,
+}
+
+/// Bit flags set on a message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct MessageFlags(u64);
+
+impl MessageFlags {
+    pub const SUPPRESS_EMBEDS: MessageFlags = MessageFlags(1 << 2);
+    /// Only the user who invoked the interaction can see the message.
+    pub const EPHEMERAL: MessageFlags = MessageFlags(1 << 6);
+
+    pub fn bits(&self) -> u64 {
+        self.0
+    }
+}
+
+impl BitOr for MessageFlags {
+    type Output = MessageFlags;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        MessageFlags(self.0 | rhs.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Embed {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<EmbedField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<EmbedThumbnail>,
+}
+
+impl Embed {
+    pub fn new(title: impl Into<String>) -> Self {
+        Embed {
+            kind: "rich",
+            title: title.into(),
+            description: None,
+            color: None,
+            fields: vec![],
+            thumbnail: None,
+        }
+    }
+
+    /// A rich embed carrying the Factorio logo, used for all server messages.
+    pub fn factorio(title: impl Into<String>, color: u32) -> Self {
+        Embed::new(title).color(color).thumbnail(FACTORIO_THUMBNAIL)
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn color(mut self, color: u32) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn field(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+        inline: bool,
+    ) -> Self {
+        self.fields.push(EmbedField {
+            name: name.into(),
+            value: value.into(),
+            inline,
+        });
+        self
+    }
+
+    pub fn thumbnail(mut self, url: impl Into<String>) -> Self {
+        self.thumbnail = Some(EmbedThumbnail {
+            url: url.into(),
+            height: 0,
+            width: 0,
+        });
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    pub inline: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbedThumbnail {
+    pub url: String,
+    pub height: u32,
+    pub width: u32,
+}
+
+/// A choice offered for a string option.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandOptionChoice {
+    pub name: String,
+    pub value: String,
+}
+
+impl CommandOptionChoice {
+    pub fn new(value: impl Into<String>) -> Self {
+        let value = value.into();
+        CommandOptionChoice {
+            name: value.clone(),
+            value,
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use tracing::info;
+
+use super::response::MessageData;
+use crate::config::Config;
+use crate::error::Result;
+
+/// Edits interaction messages through Discord's webhook API, which only
+/// requires the interaction token.
+pub struct DiscordWebhook {
+    client: reqwest::Client,
+    base_url: String,
+    app_id: String,
+    notify_url: Option<String>,
+}
+
+impl DiscordWebhook {
+    pub fn new(config: &Config) -> Self {
+        DiscordWebhook {
+            client: reqwest::Client::new(),
+            base_url: config.discord_api_base.clone(),
+            app_id: config.discord_app_id.clone(),
+            notify_url: config.discord_notify_webhook.clone(),
+        }
+    }
+
+    /// Replaces the original response to the interaction with `message`.
+    pub async fn edit_original(&self, token: &str, message: &MessageData) -> Result<()> {
+        let url = format!(
+            "{}/webhooks/{}/{}/messages/@original",
+            self.base_url, self.app_id, token
+        );
+        info!(url, "Sending patch to URL");
+
+        self.client
+            .patch(url)
+            .body(serde_json::to_string(message)?)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Deletes the original response, e.g. to replace it with an ephemeral follow-up.
+    pub async fn delete_original(&self, token: &str) -> Result<()> {
+        let url = format!(
+            "{}/webhooks/{}/{}/messages/@original",
+            self.base_url, self.app_id, token
+        );
+
+        self.client.delete(url).send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Sends a follow-up message to the interaction. Unlike edits of a deferred
+    /// response, follow-ups can be ephemeral.
+    pub async fn followup(&self, token: &str, message: &MessageData) -> Result<()> {
+        let url = format!("{}/webhooks/{}/{}", self.base_url, self.app_id, token);
+
+        self.client
+            .post(url)
+            .body(serde_json::to_string(message)?)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Posts a message to the notification channel, if one is configured.
+    pub async fn post_notice(&self, message: &MessageData) -> Result<()> {
+        let Some(url) = &self.notify_url else {
+            info!("No notification webhook configured, skipping notice");
+            return Ok(());
+        };
+
+        self.client
+            .post(url)
+            .body(serde_json::to_string(message)?)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
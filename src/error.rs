@@ -0,0 +1,89 @@
+//! The error type shared by the whole crate.
+//!
+//! Errors are grouped by what the user can do about them, so each kind can be
+//! turned into a helpful Discord reply by `discord::present::error`.
+
+use aws_sdk_cloudformation::error::{ProvideErrorMetadata, SdkError};
+use thiserror::Error;
+
+use crate::{config::ConfigError, model::dynamo::DeserializeError, rcon::RconError};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    /// AWS rejected the request because too many were made. Retrying later helps.
+    #[error("AWS is throttling requests: {0}")]
+    Throttled(String),
+    /// AWS rejected the request as invalid, e.g. a stack that can not be updated.
+    #[error("AWS rejected the request: {0}")]
+    Validation(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Discord API request failed")]
+    Discord(#[from] reqwest::Error),
+    /// The request from Discord, or an event, was not what we expected.
+    #[error("Bad input: {0}")]
+    BadInput(String),
+    #[error(transparent)]
+    Rcon(#[from] RconError),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// Stored or exchanged data could not be read or written.
+    #[error("Invalid data: {0}")]
+    Data(String),
+    #[error("AWS request failed")]
+    Aws(#[source] BoxError),
+}
+
+impl<E, R> From<SdkError<E, R>> for Error
+where
+    E: std::error::Error + ProvideErrorMetadata + Send + Sync + 'static,
+    R: std::fmt::Debug + Send + Sync + 'static,
+{
+    fn from(sdk_error: SdkError<E, R>) -> Self {
+        let message = sdk_error.message().unwrap_or_default().to_string();
+        match sdk_error.code() {
+            Some(
+                "Throttling"
+                | "ThrottlingException"
+                | "TooManyRequestsException"
+                | "RequestLimitExceeded"
+                | "ProvisionedThroughputExceededException",
+            ) => Error::Throttled(message),
+            Some("ValidationError" | "ValidationException" | "InvalidParameterException") => {
+                Error::Validation(message)
+            }
+            Some(code) if code.contains("NotFound") || code == "NoSuchBucket" => {
+                Error::NotFound(message)
+            }
+            _ => Error::Aws(Box::new(sdk_error)),
+        }
+    }
+}
+
+impl From<serde_dynamo::Error> for Error {
+    fn from(err: serde_dynamo::Error) -> Self {
+        Error::Data(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Data(err.to_string())
+    }
+}
+
+impl From<time::error::ComponentRange> for Error {
+    fn from(err: time::error::ComponentRange) -> Self {
+        Error::Data(err.to_string())
+    }
+}
+
+impl From<DeserializeError> for Error {
+    fn from(err: DeserializeError) -> Self {
+        Error::Data(err.to_string())
+    }
+}
@@ -1,4 +1,10 @@
 #![allow(async_fn_in_trait)]
 pub mod aws_client;
+pub mod budget;
+pub mod commands;
+pub mod config;
+pub mod cost;
 pub mod discord;
+pub mod error;
 pub mod model;
+pub mod rcon;
@@ -0,0 +1,100 @@
+use std::fmt;
+
+use super::{InstanceStatus, ServiceStatus, StackStatus};
+
+/// ASG lifecycle state of an instance that can serve the game.
+const IN_SERVICE: &str = "InService";
+/// ASG lifecycle state of an instance that is gone for good.
+const TERMINATED: &str = "Terminated";
+
+/// Where a server is in its life, as derived from its stack, instance and service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerLifecycle {
+    Stopped,
+    /// Asked to run, but the instance or factorio is not up yet.
+    Starting,
+    Running,
+    /// Asked to stop, but the instance or factorio is still up.
+    Stopping,
+    /// The last stack operation failed or is being rolled back.
+    Failed,
+    /// The stack is in a state this bot does not manage, e.g. being deleted.
+    Unknown,
+}
+
+impl ServerLifecycle {
+    /// Derives the lifecycle from snapshots of the CFN stack, ASG instance and ECS service.
+    ///
+    /// The stack's `ServerState` parameter says where the server is headed, while
+    /// the instance and service say whether it got there yet.
+    pub fn derive(
+        stack: &StackStatus,
+        instance: Option<&InstanceStatus>,
+        service: Option<&ServiceStatus>,
+    ) -> Self {
+        match stack.status.as_str() {
+            status if status.ends_with("_FAILED") => return ServerLifecycle::Failed,
+            "ROLLBACK_IN_PROGRESS"
+            | "ROLLBACK_COMPLETE"
+            | "UPDATE_ROLLBACK_IN_PROGRESS"
+            | "UPDATE_ROLLBACK_COMPLETE_CLEANUP_IN_PROGRESS" => return ServerLifecycle::Failed,
+            status
+                if status.is_empty()
+                    || status.starts_with("DELETE_")
+                    || status.starts_with("IMPORT_")
+                    || status.starts_with("REVIEW_") =>
+            {
+                return ServerLifecycle::Unknown
+            }
+            // After an update rolls back, the resources say where the server is
+            _ => {}
+        }
+
+        let in_service = instance.is_some_and(|instance| instance.lifecycle == IN_SERVICE);
+        let instance_gone = instance.is_none_or(|instance| instance.lifecycle == TERMINATED);
+        let running_tasks = service.map_or(0, |service| service.running);
+
+        match stack.server_state.as_deref() {
+            Some("Running") if in_service && running_tasks > 0 => ServerLifecycle::Running,
+            Some("Running") => ServerLifecycle::Starting,
+            Some("Stopped") if instance_gone && running_tasks == 0 => ServerLifecycle::Stopped,
+            Some("Stopped") => ServerLifecycle::Stopping,
+            _ => ServerLifecycle::Unknown,
+        }
+    }
+
+    /// Whether the server can move from this state to `next`.
+    ///
+    /// Staying in the same state is always allowed, as is any move from or to
+    /// `Unknown`, since nothing can be said about those.
+    pub fn can_transition_to(self, next: ServerLifecycle) -> bool {
+        use ServerLifecycle::*;
+
+        if self == next {
+            return true;
+        }
+        match (self, next) {
+            (Unknown, _) | (_, Unknown) => true,
+            (Stopped, Starting) => true,
+            (Starting, Running | Stopping | Failed) => true,
+            // Losing the instance, e.g. to a spot interruption, starts it over
+            (Running, Starting | Stopping | Failed) => true,
+            (Stopping, Stopped | Failed) => true,
+            (Failed, Stopped | Starting | Running | Stopping) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ServerLifecycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ServerLifecycle::Stopped => "stopped",
+            ServerLifecycle::Starting => "starting",
+            ServerLifecycle::Running => "running",
+            ServerLifecycle::Stopping => "stopping",
+            ServerLifecycle::Failed => "failed",
+            ServerLifecycle::Unknown => "in an unknown state",
+        })
+    }
+}
@@ -1,7 +1,301 @@
-use time::OffsetDateTime;
+use serde::Deserialize;
+use time::{Duration, OffsetDateTime};
+
+mod lifecycle;
+
+pub use lifecycle::ServerLifecycle;
 
 #[derive(Debug)]
 pub struct StartServerInteraction {
+    pub server: String,
+    pub token: String,
+    pub timestamp: OffsetDateTime,
+    /// The `ClientRequestToken` of the stack update the start made.
+    pub request_token: String,
+}
+
+#[derive(Debug)]
+pub struct StopServerInteraction {
+    pub server: String,
     pub token: String,
     pub timestamp: OffsetDateTime,
+    /// The `ClientRequestToken` of the stack update the stop made.
+    pub request_token: String,
+    /// When the instance being stopped was launched, to report the uptime.
+    pub launched_at: Option<OffsetDateTime>,
+}
+
+/// The AWS resources making up one named factorio server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerDefinition {
+    pub name: String,
+    pub stack_name: String,
+    pub cluster_name: String,
+    pub service_name: String,
+    pub asg_name: String,
+}
+
+/// An instance type the server may be started on, with the most we are
+/// willing to pay for it per hour as a spot instance.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct InstanceSize {
+    pub instance_type: String,
+    pub spot_price: String,
+}
+
+/// Result of asking CloudFormation to move the server into a new state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    /// The stack update was accepted and is now running.
+    Updating,
+    /// The server is already in the requested state.
+    AlreadyInState,
+    /// Another stack update is still in progress.
+    UpdateInProgress,
+}
+
+/// What can be said about the server's address right now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerIpStatus {
+    NotRunning,
+    /// The instance exists but is not in service, e.g. `Pending`.
+    InstanceNotReady(String),
+    /// The instance has an IP, but factorio is not running on it yet.
+    Starting {
+        ip: String,
+    },
+    Running {
+        ip: String,
+    },
+}
+
+/// A player connected to the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnlinePlayer {
+    pub name: String,
+    /// Time played in the save over all sessions, not just the current one.
+    /// Only known when RCON may run Lua.
+    pub online_time: Option<Duration>,
+}
+
+/// Who is playing, as far as can be told from outside the game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayersStatus {
+    NotRunning,
+    RconDisabled,
+    /// The server is up, but RCON did not answer, e.g. while factorio is loading.
+    Unreachable,
+    Online {
+        /// Every player that has ever joined the save.
+        total: usize,
+        online: Vec<OnlinePlayer>,
+    },
+}
+
+/// Steps of a graceful stop, reported while they happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopProgress {
+    /// Players have been warned in-game that the server is going down.
+    Warning {
+        remaining: Duration,
+    },
+    Saving,
+}
+
+/// Everything known about a server, gathered from its CFN stack, ASG and ECS service.
+#[derive(Debug, Clone)]
+pub struct ServerStatus {
+    pub stack: StackStatus,
+    pub instance: Option<InstanceStatus>,
+    pub service: Option<ServiceStatus>,
+}
+
+impl ServerStatus {
+    pub fn lifecycle(&self) -> ServerLifecycle {
+        ServerLifecycle::derive(&self.stack, self.instance.as_ref(), self.service.as_ref())
+    }
+
+    /// The IP of the instance, once it is in service. Factorio may not be running yet.
+    pub fn instance_ip(&self) -> Option<&str> {
+        self.instance
+            .as_ref()
+            .filter(|instance| instance.lifecycle == "InService")
+            .and_then(|instance| instance.ip.as_deref())
+    }
+
+    /// The IP factorio can be reached at, if it is running.
+    pub fn running_ip(&self) -> Option<&str> {
+        match self.lifecycle() {
+            ServerLifecycle::Running => self.instance_ip(),
+            _ => None,
+        }
+    }
+
+    pub fn ip_status(&self) -> ServerIpStatus {
+        match (self.lifecycle(), &self.instance, self.instance_ip()) {
+            (ServerLifecycle::Running, _, Some(ip)) => {
+                ServerIpStatus::Running { ip: ip.to_string() }
+            }
+            (ServerLifecycle::Starting, _, Some(ip)) => {
+                ServerIpStatus::Starting { ip: ip.to_string() }
+            }
+            (ServerLifecycle::Starting, Some(instance), None) => {
+                ServerIpStatus::InstanceNotReady(instance.lifecycle.clone())
+            }
+            _ => ServerIpStatus::NotRunning,
+        }
+    }
+}
+
+/// The CFN stack's status and the parameters it was last deployed with.
+#[derive(Debug, Clone, Default)]
+pub struct StackStatus {
+    pub status: String,
+    pub server_state: Option<String>,
+    /// The save directory, without the slashes of the `MountingDir` parameter.
+    pub save: Option<String>,
+    pub instance_type: Option<String>,
+    pub spot_price: Option<String>,
+    pub image_tag: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstanceStatus {
+    pub instance_id: String,
+    pub lifecycle: String,
+    pub ip: Option<String>,
+    pub launched_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServiceStatus {
+    /// Rollout state of the primary deployment, e.g. `IN_PROGRESS`.
+    pub rollout: Option<String>,
+    pub desired: i32,
+    pub running: i32,
+    pub pending: i32,
+    /// Last known status of the factorio task, e.g. `PROVISIONING` or `RUNNING`.
+    pub task: Option<String>,
+}
+
+/// The resource that made a stack operation fail, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFailure {
+    pub resource: String,
+    pub reason: String,
+}
+
+/// The spending limit across all servers, per calendar month.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Budget {
+    /// In US dollars.
+    pub amount: f64,
+    /// The last usage warning posted, so each threshold is announced once a month.
+    pub warned: Option<BudgetWarning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetWarning {
+    /// The month the warning was posted in, e.g. `2024-03`.
+    pub month: String,
+    pub percent: u32,
+}
+
+/// How much of this month's budget is spent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BudgetUsage {
+    pub amount: f64,
+    pub spent: f64,
+}
+
+impl BudgetUsage {
+    pub fn percent_used(&self) -> f64 {
+        if self.amount > 0.0 {
+            self.spent / self.amount * 100.0
+        } else {
+            100.0
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.spent >= self.amount
+    }
+}
+
+/// Where an instance runs, which determines its spot price.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstancePlacement {
+    pub instance_type: String,
+    pub availability_zone: String,
+}
+
+/// The hourly spot price in effect from `timestamp` on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpotPrice {
+    pub timestamp: OffsetDateTime,
+    pub price: f64,
+}
+
+/// What an instance cost from its launch until a spot interruption reclaimed it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstanceCost {
+    pub launched_at: OffsetDateTime,
+    pub interrupted_at: OffsetDateTime,
+    /// In US dollars.
+    pub cost: f64,
+}
+
+impl InstanceCost {
+    /// The share of the cost that fell after `since`, assuming the price held
+    /// steady while the instance ran.
+    pub fn cost_since(&self, since: OffsetDateTime) -> f64 {
+        prorate(self.cost, self.launched_at, self.interrupted_at, since)
+    }
+}
+
+/// The share of `cost`, spread evenly from `from` to `to`, that fell after `since`.
+fn prorate(cost: f64, from: OffsetDateTime, to: OffsetDateTime, since: OffsetDateTime) -> f64 {
+    if since <= from {
+        return cost;
+    }
+    if since >= to {
+        return 0.0;
+    }
+    cost * ((to - since) / (to - from))
+}
+
+/// One run of a server, from the start command until it stopped.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub server: String,
+    pub started_at: OffsetDateTime,
+    pub started_by: String,
+    pub save: String,
+    pub instance_type: Option<String>,
+    /// Time from the start command until factorio answered.
+    pub ready_after: Option<Duration>,
+    pub stopped_by: Option<String>,
+    pub stopped_at: Option<OffsetDateTime>,
+    /// What the instances cost in US dollars, known once the session was stopped.
+    pub cost: Option<f64>,
+    /// Instances the session lost to spot interruptions, oldest first.
+    pub interrupted: Vec<InstanceCost>,
+}
+
+impl Session {
+    /// How long the session ran, if it has ended.
+    pub fn duration(&self) -> Option<Duration> {
+        self.stopped_at
+            .map(|stopped_at| stopped_at - self.started_at)
+    }
+
+    /// The share of the settled cost that fell after `since`, assuming it was
+    /// spread evenly over the session.
+    pub fn cost_since(&self, since: OffsetDateTime) -> Option<f64> {
+        let cost = self.cost?;
+        Some(match self.stopped_at {
+            Some(stopped_at) => prorate(cost, self.started_at, stopped_at, since),
+            None => cost,
+        })
+    }
 }
@@ -3,20 +3,42 @@ use std::ops::Add;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use super::domain::StartServerInteraction;
+use crate::discord::INTERACTION_TOKEN_LIFETIME;
+
+use super::domain::{
+    Budget, BudgetWarning, InstanceCost, ServerDefinition, Session, StartServerInteraction,
+    StopServerInteraction,
+};
 use time::{ext::NumericalDuration, OffsetDateTime};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Command {
     FactorioStart,
+    FactorioStop,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct DiscordInteraction {
     pub command: Command,
     pub timestamp: i64,
+    #[serde(default)]
+    pub server: String,
     token: String,
     ttl: i64,
+    /// Ties the interaction to the events of the stack update it made.
+    #[serde(default)]
+    pub request_token: String,
+    /// Launch time of the instance, for stops.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    launched_at: Option<i64>,
+}
+
+impl DiscordInteraction {
+    /// The item's key: its command, then the `ClientRequestToken` of the
+    /// interaction, which unlike its time is unique.
+    pub fn key(&self) -> (Command, &str) {
+        (self.command, &self.request_token)
+    }
 }
 
 impl From<StartServerInteraction> for DiscordInteraction {
@@ -24,8 +46,33 @@ impl From<StartServerInteraction> for DiscordInteraction {
         DiscordInteraction {
             command: Command::FactorioStart,
             timestamp: value.timestamp.unix_timestamp(),
+            server: value.server,
             token: value.token,
-            ttl: value.timestamp.add(15.minutes()).unix_timestamp(),
+            ttl: value
+                .timestamp
+                .add(INTERACTION_TOKEN_LIFETIME)
+                .unix_timestamp(),
+            request_token: value.request_token,
+            launched_at: None,
+        }
+    }
+}
+
+impl From<StopServerInteraction> for DiscordInteraction {
+    fn from(value: StopServerInteraction) -> Self {
+        DiscordInteraction {
+            command: Command::FactorioStop,
+            timestamp: value.timestamp.unix_timestamp(),
+            server: value.server,
+            token: value.token,
+            ttl: value
+                .timestamp
+                .add(INTERACTION_TOKEN_LIFETIME)
+                .unix_timestamp(),
+            request_token: value.request_token,
+            launched_at: value
+                .launched_at
+                .map(|launched_at| launched_at.unix_timestamp()),
         }
     }
 }
@@ -45,9 +92,208 @@ impl TryInto<StartServerInteraction> for DiscordInteraction {
         } else {
             Ok(StartServerInteraction {
                 timestamp: OffsetDateTime::from_unix_timestamp(self.timestamp)
-                    .expect("Invalid unix timestamp"),
+                    .map_err(|_| DeserializeError::Error)?,
+                server: self.server,
                 token: self.token,
+                request_token: self.request_token,
             })
         }
     }
 }
+
+impl TryInto<StopServerInteraction> for DiscordInteraction {
+    type Error = DeserializeError;
+
+    fn try_into(self) -> Result<StopServerInteraction, Self::Error> {
+        if !matches!(self.command, Command::FactorioStop) {
+            return Err(DeserializeError::Error);
+        }
+        let timestamp = |seconds| {
+            OffsetDateTime::from_unix_timestamp(seconds).map_err(|_| DeserializeError::Error)
+        };
+        Ok(StopServerInteraction {
+            timestamp: timestamp(self.timestamp)?,
+            launched_at: self.launched_at.map(timestamp).transpose()?,
+            server: self.server,
+            token: self.token,
+            request_token: self.request_token,
+        })
+    }
+}
+
+/// Marks a running server that has had no players online since `idle_since`.
+#[derive(Serialize, Deserialize)]
+pub struct IdleServer {
+    pub server: String,
+    pub idle_since: i64,
+}
+
+/// A server session, keyed by server and start time.
+#[derive(Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub server: String,
+    pub started_at: i64,
+    pub started_by: String,
+    pub save: String,
+    #[serde(default)]
+    pub instance_type: Option<String>,
+    /// Seconds from the start command until factorio answered.
+    #[serde(default)]
+    pub ready_after: Option<i64>,
+    #[serde(default)]
+    pub stopped_by: Option<String>,
+    #[serde(default)]
+    pub stopped_at: Option<i64>,
+    /// In US dollars.
+    #[serde(default)]
+    pub cost: Option<f64>,
+    #[serde(default)]
+    pub interrupted: Vec<InstanceCostRecord>,
+}
+
+/// An instance a session lost to a spot interruption.
+#[derive(Serialize, Deserialize)]
+pub struct InstanceCostRecord {
+    pub launched_at: i64,
+    pub interrupted_at: i64,
+    /// In US dollars.
+    pub cost: f64,
+}
+
+impl From<&Session> for SessionRecord {
+    fn from(value: &Session) -> Self {
+        SessionRecord {
+            server: value.server.clone(),
+            started_at: value.started_at.unix_timestamp(),
+            started_by: value.started_by.clone(),
+            save: value.save.clone(),
+            instance_type: value.instance_type.clone(),
+            ready_after: value.ready_after.map(|ready| ready.whole_seconds()),
+            stopped_by: value.stopped_by.clone(),
+            stopped_at: value.stopped_at.map(|stopped| stopped.unix_timestamp()),
+            cost: value.cost,
+            interrupted: value
+                .interrupted
+                .iter()
+                .map(|instance| InstanceCostRecord {
+                    launched_at: instance.launched_at.unix_timestamp(),
+                    interrupted_at: instance.interrupted_at.unix_timestamp(),
+                    cost: instance.cost,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<SessionRecord> for Session {
+    type Error = DeserializeError;
+
+    fn try_from(value: SessionRecord) -> Result<Self, Self::Error> {
+        let timestamp = |seconds| {
+            OffsetDateTime::from_unix_timestamp(seconds).map_err(|_| DeserializeError::Error)
+        };
+        Ok(Session {
+            started_at: timestamp(value.started_at)?,
+            stopped_at: value.stopped_at.map(timestamp).transpose()?,
+            ready_after: value.ready_after.map(|seconds| seconds.seconds()),
+            server: value.server,
+            started_by: value.started_by,
+            save: value.save,
+            instance_type: value.instance_type,
+            stopped_by: value.stopped_by,
+            cost: value.cost,
+            interrupted: value
+                .interrupted
+                .into_iter()
+                .map(|instance| {
+                    Ok(InstanceCost {
+                        launched_at: timestamp(instance.launched_at)?,
+                        interrupted_at: timestamp(instance.interrupted_at)?,
+                        cost: instance.cost,
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// A spot interruption warning received for a server's instance.
+#[derive(Serialize, Deserialize)]
+pub struct SpotInterruption {
+    pub server: String,
+    pub timestamp: i64,
+    pub instance_id: String,
+    /// Whether the game was saved before the instance was reclaimed.
+    pub saved: bool,
+}
+
+/// The monthly budget. There is a single one, stored under [`BudgetRecord::NAME`].
+#[derive(Serialize, Deserialize)]
+pub struct BudgetRecord {
+    pub name: String,
+    /// In US dollars.
+    pub amount: f64,
+    #[serde(default)]
+    pub warned_month: Option<String>,
+    #[serde(default)]
+    pub warned_percent: Option<u32>,
+}
+
+impl BudgetRecord {
+    pub const NAME: &'static str = "monthly";
+}
+
+impl From<&Budget> for BudgetRecord {
+    fn from(value: &Budget) -> Self {
+        BudgetRecord {
+            name: BudgetRecord::NAME.to_string(),
+            amount: value.amount,
+            warned_month: value.warned.as_ref().map(|warned| warned.month.clone()),
+            warned_percent: value.warned.as_ref().map(|warned| warned.percent),
+        }
+    }
+}
+
+impl From<BudgetRecord> for Budget {
+    fn from(value: BudgetRecord) -> Self {
+        Budget {
+            amount: value.amount,
+            warned: value
+                .warned_month
+                .zip(value.warned_percent)
+                .map(|(month, percent)| BudgetWarning { month, percent }),
+        }
+    }
+}
+
+/// An entry of the server registry. Only the name and stack are required, the
+/// other resources default to the naming used by the CloudFormation template.
+#[derive(Serialize, Deserialize)]
+pub struct ServerRecord {
+    pub name: String,
+    pub stack_name: String,
+    #[serde(default)]
+    pub cluster_name: Option<String>,
+    #[serde(default)]
+    pub service_name: Option<String>,
+    #[serde(default)]
+    pub asg_name: Option<String>,
+}
+
+impl From<ServerRecord> for ServerDefinition {
+    fn from(value: ServerRecord) -> Self {
+        ServerDefinition {
+            cluster_name: value
+                .cluster_name
+                .unwrap_or_else(|| format!("{}-cluster", value.stack_name)),
+            service_name: value
+                .service_name
+                .unwrap_or_else(|| format!("{}-ecs-service", value.stack_name)),
+            asg_name: value
+                .asg_name
+                .unwrap_or_else(|| format!("{}-asg", value.stack_name)),
+            name: value.name,
+            stack_name: value.stack_name,
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Async client for the Source RCON protocol, as spoken by Factorio's `--rcon-port`.
+
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tracing::{info, warn};
+
+use crate::{config::RconSettings, model::domain::OnlinePlayer};
+use packet::{
+    Packet, MAX_REQUEST_SIZE, SERVERDATA_AUTH, SERVERDATA_AUTH_RESPONSE, SERVERDATA_EXECCOMMAND,
+    SERVERDATA_RESPONSE_VALUE,
+};
+
+pub mod packet;
+
+/// Port factorio listens on for RCON when enabled in the CFN template.
+pub const DEFAULT_PORT: u16 = 27015;
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Large maps can take a while to save, during which factorio does not answer.
+pub const SAVE_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Error, Debug)]
+pub enum RconError {
+    #[error("Could not connect to the RCON server")]
+    Connect(#[source] std::io::Error),
+    #[error("RCON connection failed")]
+    Io(#[from] std::io::Error),
+    #[error("RCON request timed out")]
+    Timeout,
+    #[error("RCON password was rejected")]
+    AuthFailed,
+    #[error("Command is too long to send over RCON")]
+    CommandTooLong,
+    #[error("Unexpected RCON response: {0}")]
+    Protocol(String),
+}
+
+/// An authenticated RCON connection.
+pub struct RconClient {
+    stream: TcpStream,
+    next_id: i32,
+    timeout: Duration,
+}
+
+impl RconClient {
+    /// Connects and authenticates, failing if either takes longer than `timeout`.
+    pub async fn connect(
+        addr: impl ToSocketAddrs,
+        password: &str,
+        timeout: Duration,
+    ) -> Result<Self, RconError> {
+        let stream = tokio::time::timeout(timeout, TcpStream::connect(addr))
+            .await
+            .map_err(|_| RconError::Timeout)?
+            .map_err(RconError::Connect)?;
+
+        let mut client = RconClient {
+            stream,
+            next_id: 1,
+            timeout,
+        };
+        client.authenticate(password).await?;
+        Ok(client)
+    }
+
+    /// Connects to factorio running at `ip`, using the deployment's RCON settings.
+    pub async fn connect_to(ip: &str, settings: &RconSettings) -> Result<Self, RconError> {
+        Self::connect((ip, settings.port), &settings.password, DEFAULT_TIMEOUT).await
+    }
+
+    async fn authenticate(&mut self, password: &str) -> Result<(), RconError> {
+        let id = self.next_id();
+        with_timeout(self.timeout, async {
+            Packet::new(id, SERVERDATA_AUTH, password)
+                .write_to(&mut self.stream)
+                .await?;
+
+            // Some servers send an empty response value before the auth response.
+            loop {
+                let packet = Packet::read_from(&mut self.stream).await?;
+                match packet.kind {
+                    SERVERDATA_AUTH_RESPONSE if packet.id == id => return Ok(()),
+                    SERVERDATA_AUTH_RESPONSE if packet.id == -1 => {
+                        return Err(RconError::AuthFailed)
+                    }
+                    SERVERDATA_RESPONSE_VALUE => continue,
+                    _ => {
+                        return Err(RconError::Protocol(format!(
+                            "expected auth response, got {:?}",
+                            packet
+                        )))
+                    }
+                }
+            }
+        })
+        .await
+    }
+
+    /// Runs a console command and returns its output.
+    ///
+    /// Responses can be split over several packets, so an empty command is sent
+    /// right after. Its response marks the end of the real command's output.
+    pub async fn exec(&mut self, command: &str) -> Result<String, RconError> {
+        if command.len() > MAX_REQUEST_SIZE - 14 {
+            return Err(RconError::CommandTooLong);
+        }
+
+        let id = self.next_id();
+        let sentinel_id = self.next_id();
+        info!(id, command, "sending RCON command");
+
+        with_timeout(self.timeout, async {
+            Packet::new(id, SERVERDATA_EXECCOMMAND, command)
+                .write_to(&mut self.stream)
+                .await?;
+            Packet::new(sentinel_id, SERVERDATA_EXECCOMMAND, "")
+                .write_to(&mut self.stream)
+                .await?;
+
+            let mut output = String::new();
+            loop {
+                let packet = Packet::read_from(&mut self.stream).await?;
+                if packet.id == sentinel_id {
+                    return Ok(output);
+                } else if packet.id == id && packet.kind == SERVERDATA_RESPONSE_VALUE {
+                    output.push_str(&packet.body);
+                } else {
+                    warn!(?packet, "ignoring unexpected RCON packet");
+                }
+            }
+        })
+        .await
+    }
+
+    /// Names of the players that are currently connected.
+    pub async fn players_online(&mut self) -> Result<Vec<String>, RconError> {
+        Ok(parse_players(&self.exec("/players online").await?))
+    }
+
+    /// The number of players that ever joined, and the ones online right now.
+    ///
+    /// Uses the built-in `/players` command, which leaves achievements alone.
+    pub async fn online_players(&mut self) -> Result<(usize, Vec<OnlinePlayer>), RconError> {
+        Ok(parse_player_list(&self.exec("/players").await?))
+    }
+
+    /// Like `online_players`, with the time each player has spent in the save.
+    ///
+    /// This runs Lua, which disables achievements on the save for good.
+    pub async fn online_players_with_play_time(
+        &mut self,
+    ) -> Result<(usize, Vec<OnlinePlayer>), RconError> {
+        let output = self
+            .silent_command(
+                "rcon.print(#game.players) \
+                 for _, p in pairs(game.connected_players) do \
+                 rcon.print(p.name .. ' ' .. p.online_time) end",
+            )
+            .await?;
+        parse_online_players(&output)
+    }
+
+    /// Saves the game under its current name.
+    pub async fn server_save(&mut self) -> Result<String, RconError> {
+        self.exec("/server-save").await
+    }
+
+    /// Saves the game and waits for the save to be written.
+    ///
+    /// Factorio runs console commands on its main thread, which is blocked
+    /// while saving, so the answer to a follow-up command only arrives once
+    /// the save is done.
+    pub async fn server_save_and_wait(&mut self) -> Result<String, RconError> {
+        let timeout = std::mem::replace(&mut self.timeout, SAVE_TIMEOUT);
+        let result = async {
+            let output = self.server_save().await?;
+            self.exec("/version").await?;
+            Ok(output)
+        }
+        .await;
+        self.timeout = timeout;
+        result
+    }
+
+    /// Sends a chat message to every player as the server.
+    pub async fn broadcast(&mut self, message: &str) -> Result<(), RconError> {
+        // Anything starting with a slash would be run as a command
+        self.exec(message.trim_start_matches('/')).await?;
+        Ok(())
+    }
+
+    /// Runs Lua without printing it to the in-game console. Use `rcon.print`
+    /// to send output back.
+    ///
+    /// Note that this disables achievements on the save, like any Lua command.
+    pub async fn silent_command(&mut self, lua: &str) -> Result<String, RconError> {
+        self.exec(&format!("/silent-command {}", lua)).await
+    }
+
+    fn next_id(&mut self) -> i32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+        id
+    }
+}
+
+async fn with_timeout<T>(
+    timeout: Duration,
+    future: impl std::future::Future<Output = Result<T, RconError>>,
+) -> Result<T, RconError> {
+    tokio::time::timeout(timeout, future)
+        .await
+        .map_err(|_| RconError::Timeout)?
+}
+
+/// Parses the output of `/players` or `/players online`, e.g.
+///
+/// ```text
+/// Online players (2):
+///   alice (online)
+///   bob (online)
+/// ```
+pub fn parse_players(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip(1)
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.trim_end_matches(" (online)").to_string())
+        .collect()
+}
+
+/// Parses the output of `/players`: a header, then every player that ever
+/// joined, with connected ones marked `(online)`.
+pub fn parse_player_list(output: &str) -> (usize, Vec<OnlinePlayer>) {
+    let players: Vec<&str> = output
+        .lines()
+        .skip(1)
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+    let online = players
+        .iter()
+        .filter_map(|line| line.strip_suffix(" (online)"))
+        .map(|name| OnlinePlayer {
+            name: name.to_string(),
+            online_time: None,
+        })
+        .collect();
+    (players.len(), online)
+}
+
+/// Factorio runs at a fixed 60 ticks per second.
+const TICKS_PER_SECOND: i64 = 60;
+
+/// Parses the output of the script in `online_players_with_play_time`: the total number of
+/// players, then one `<name> <online ticks>` line per connected player.
+fn parse_online_players(output: &str) -> Result<(usize, Vec<OnlinePlayer>), RconError> {
+    let invalid = || RconError::Protocol(format!("unexpected players output {:?}", output));
+
+    let mut lines = output.lines().filter(|line| !line.trim().is_empty());
+    let total = lines
+        .next()
+        .and_then(|line| line.trim().parse().ok())
+        .ok_or_else(invalid)?;
+
+    let online = lines
+        .map(|line| {
+            let (name, ticks) = line.trim().rsplit_once(' ').ok_or_else(invalid)?;
+            let ticks: i64 = ticks.parse().map_err(|_| invalid())?;
+            Ok(OnlinePlayer {
+                name: name.to_string(),
+                online_time: Some(time::Duration::seconds(ticks / TICKS_PER_SECOND)),
+            })
+        })
+        .collect::<Result<_, RconError>>()?;
+
+    Ok((total, online))
+}
@@ -0,0 +1,73 @@
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::RconError;
+
+pub const SERVERDATA_AUTH: i32 = 3;
+pub const SERVERDATA_AUTH_RESPONSE: i32 = 2;
+pub const SERVERDATA_EXECCOMMAND: i32 = 2;
+pub const SERVERDATA_RESPONSE_VALUE: i32 = 0;
+
+/// Size of the ID, type and the two null terminators.
+const HEADER_SIZE: i32 = 10;
+/// Largest packet the Source protocol lets clients send.
+pub const MAX_REQUEST_SIZE: usize = 4096;
+/// Factorio does not split large responses, so accept more than the protocol limit.
+const MAX_RESPONSE_SIZE: i32 = 1024 * 1024;
+
+/// A Source RCON packet, see https://developer.valvesoftware.com/wiki/Source_RCON_Protocol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    pub id: i32,
+    pub kind: i32,
+    pub body: String,
+}
+
+impl Packet {
+    pub fn new(id: i32, kind: i32, body: &str) -> Self {
+        Packet {
+            id,
+            kind,
+            body: body.to_string(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let size = HEADER_SIZE + self.body.len() as i32;
+        let mut bytes = Vec::with_capacity(size as usize + 4);
+        bytes.extend_from_slice(&size.to_le_bytes());
+        bytes.extend_from_slice(&self.id.to_le_bytes());
+        bytes.extend_from_slice(&self.kind.to_le_bytes());
+        bytes.extend_from_slice(self.body.as_bytes());
+        bytes.extend_from_slice(&[0, 0]);
+        bytes
+    }
+
+    pub async fn write_to<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> Result<(), RconError> {
+        writer.write_all(&self.encode()).await?;
+        Ok(())
+    }
+
+    pub async fn read_from<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Packet, RconError> {
+        let size = reader.read_i32_le().await?;
+        if !(HEADER_SIZE..=MAX_RESPONSE_SIZE).contains(&size) {
+            return Err(RconError::Protocol(format!("invalid packet size {}", size)));
+        }
+
+        let id = reader.read_i32_le().await?;
+        let kind = reader.read_i32_le().await?;
+        let mut body = vec![0; (size - 8) as usize];
+        reader.read_exact(&mut body).await?;
+
+        if body.split_off(body.len() - 2) != [0, 0] {
+            return Err(RconError::Protocol(
+                "packet is not null terminated".to_string(),
+            ));
+        }
+
+        Ok(Packet {
+            id,
+            kind,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use factorio_server_lambda::{
+    commands::authz::{CommandPolicies, CommandPolicy},
+    config::Config,
+    discord::model::Interaction,
+};
+use serde_json::json;
+
+const ADMIN_ROLE: &str = "1100000000000000001";
+const ADMIN_USER: &str = "1200000000000000001";
+const OTHER_USER: &str = "1200000000000000002";
+
+/// A command invoked in a guild, by a member with the given roles.
+fn in_guild(user_id: &str, roles: &[&str]) -> Interaction {
+    serde_json::from_value(json!({
+        "id": "1300000000000000001",
+        "application_id": "1400000000000000001",
+        "type": 2,
+        "token": "token",
+        "guild_id": "1500000000000000001",
+        "member": {
+            "user": { "id": user_id, "username": "player" },
+            "roles": roles,
+        },
+    }))
+    .unwrap()
+}
+
+/// A command invoked in a DM, which has a user but no member or roles.
+fn in_dm(user_id: &str) -> Interaction {
+    serde_json::from_value(json!({
+        "id": "1300000000000000001",
+        "application_id": "1400000000000000001",
+        "type": 2,
+        "token": "token",
+        "user": { "id": user_id, "username": "player" },
+    }))
+    .unwrap()
+}
+
+fn admin_policy() -> CommandPolicy {
+    CommandPolicy {
+        roles: vec![ADMIN_ROLE.to_string()],
+        users: vec![ADMIN_USER.to_string()],
+    }
+}
+
+fn policies() -> CommandPolicies {
+    serde_json::from_value(json!({
+        "stop": { "roles": [ADMIN_ROLE], "users": [ADMIN_USER] },
+    }))
+    .unwrap()
+}
+
+#[test]
+fn allows_a_member_with_a_listed_role() {
+    assert!(admin_policy().allows(&in_guild(OTHER_USER, &["1", ADMIN_ROLE])));
+}
+
+#[test]
+fn allows_a_listed_user_without_the_role() {
+    assert!(admin_policy().allows(&in_guild(ADMIN_USER, &[])));
+}
+
+#[test]
+fn denies_a_member_without_role_or_listing() {
+    assert!(!admin_policy().allows(&in_guild(OTHER_USER, &["1"])));
+}
+
+#[test]
+fn allows_a_listed_user_in_a_dm() {
+    assert!(admin_policy().allows(&in_dm(ADMIN_USER)));
+}
+
+#[test]
+fn denies_other_users_in_a_dm() {
+    assert!(!admin_policy().allows(&in_dm(OTHER_USER)));
+}
+
+#[test]
+fn empty_policy_allows_nobody() {
+    assert!(!CommandPolicy::default().allows(&in_guild(ADMIN_USER, &[ADMIN_ROLE])));
+}
+
+#[test]
+fn applies_the_policy_of_the_command() {
+    let policies = policies();
+
+    assert!(policies.is_authorized("stop", &in_guild(OTHER_USER, &[ADMIN_ROLE])));
+    assert!(!policies.is_authorized("stop", &in_guild(OTHER_USER, &[])));
+    assert!(!policies.is_authorized("stop", &in_dm(OTHER_USER)));
+}
+
+#[test]
+fn commands_without_a_policy_are_open() {
+    let policies = policies();
+
+    assert!(policies.is_authorized("start", &in_guild(OTHER_USER, &[])));
+    assert!(policies.is_authorized("start", &in_dm(OTHER_USER)));
+    assert!(CommandPolicies::default().is_authorized("stop", &in_dm(OTHER_USER)));
+}
+
+#[test]
+fn loads_policies_from_the_config() {
+    let values: HashMap<String, String> = [
+        ("DISCORD_APP_ID", "1400000000000000001"),
+        (
+            "DISCORD_PUBLIC_KEY",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+        ),
+        (
+            "FACTORIO_COMMAND_POLICIES",
+            r#"{"stop": {"roles": ["1100000000000000001"]}}"#,
+        ),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), value.to_string()))
+    .collect();
+    let policies = Config::from_values(&values).unwrap().command_policies;
+
+    assert!(policies.is_authorized("stop", &in_guild(OTHER_USER, &[ADMIN_ROLE])));
+    assert!(!policies.is_authorized("stop", &in_guild(ADMIN_USER, &[])));
+}
+
+#[test]
+fn refuses_privately() {
+    let policies = policies();
+
+    assert!(policies
+        .refusal("stop", &in_guild(ADMIN_USER, &[]))
+        .is_none());
+
+    let refusal = policies.refusal("stop", &in_dm(OTHER_USER)).unwrap();
+    let refusal = serde_json::to_value(refusal).unwrap();
+    assert_eq!(refusal["type"], 4);
+    assert_eq!(refusal["data"]["flags"], 1 << 6);
+}
@@ -0,0 +1,55 @@
+use factorio_server_lambda::{budget::threshold_reached, model::domain::BudgetUsage};
+
+const THRESHOLDS: [u32; 3] = [50, 80, 100];
+
+fn usage(spent: f64) -> BudgetUsage {
+    BudgetUsage {
+        amount: 20.0,
+        spent,
+    }
+}
+
+#[test]
+fn nothing_to_announce_below_the_first_threshold() {
+    assert_eq!(threshold_reached(&THRESHOLDS, &usage(9.99), None), None);
+}
+
+#[test]
+fn announces_the_highest_threshold_reached() {
+    assert_eq!(threshold_reached(&THRESHOLDS, &usage(10.0), None), Some(50));
+    assert_eq!(threshold_reached(&THRESHOLDS, &usage(17.0), None), Some(80));
+    assert_eq!(
+        threshold_reached(&THRESHOLDS, &usage(25.0), None),
+        Some(100)
+    );
+}
+
+#[test]
+fn announces_each_threshold_once() {
+    assert_eq!(threshold_reached(&THRESHOLDS, &usage(12.0), Some(50)), None);
+    assert_eq!(
+        threshold_reached(&THRESHOLDS, &usage(17.0), Some(50)),
+        Some(80)
+    );
+    assert_eq!(
+        threshold_reached(&THRESHOLDS, &usage(25.0), Some(100)),
+        None
+    );
+}
+
+#[test]
+fn budget_is_exhausted_once_fully_spent() {
+    assert!(!usage(19.99).is_exhausted());
+    assert!(usage(20.0).is_exhausted());
+    assert_eq!(usage(5.0).percent_used(), 25.0);
+}
+
+#[test]
+fn zero_budget_counts_as_spent() {
+    let usage = BudgetUsage {
+        amount: 0.0,
+        spent: 0.0,
+    };
+    assert!(usage.is_exhausted());
+    assert_eq!(threshold_reached(&THRESHOLDS, &usage, None), Some(100));
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use factorio_server_lambda::config::{Config, ConfigError};
+use time::Duration;
+
+/// The public key of RFC 8032's first ed25519 test vector.
+const PUBLIC_KEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+/// The least a deployment has to set.
+fn required() -> HashMap<String, String> {
+    values(&[
+        ("DISCORD_APP_ID", "1400000000000000001"),
+        ("DISCORD_PUBLIC_KEY", PUBLIC_KEY),
+    ])
+}
+
+fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn with(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    let mut values = required();
+    values.extend(self::values(pairs));
+    values
+}
+
+fn invalid_key(result: Result<Config, ConfigError>) -> &'static str {
+    match result {
+        Err(ConfigError::Invalid { key, .. }) => key,
+        other => panic!("expected an invalid setting, got {:?}", other),
+    }
+}
+
+#[test]
+fn defaults_everything_but_the_discord_keys() {
+    let config = Config::from_values(&required()).unwrap();
+
+    assert_eq!(config.saves_bucket, None);
+    assert_eq!(config.saves_prefix, "saves/");
+    assert!(config.rcon.is_none());
+    assert_eq!(config.idle_shutdown, Duration::minutes(30));
+    assert_eq!(config.budget_thresholds, [50, 80, 100]);
+}
+
+#[test]
+fn names_resources_from_the_settings() {
+    assert_eq!(
+        Config::from_values(&required()).unwrap().interactions_table,
+        "discord-interaction-tokens"
+    );
+
+    let config = Config::from_values(&with(&[
+        ("FACTORIO_INTERACTIONS_TABLE", "staging-interactions"),
+        ("FACTORIO_SERVERS_TABLE", "staging-servers"),
+        ("DISCORD_APP_ID", "1400000000000000002"),
+    ]))
+    .unwrap();
+
+    assert_eq!(config.interactions_table, "staging-interactions");
+    assert_eq!(config.servers_table, "staging-servers");
+    assert_eq!(config.discord_app_id, "1400000000000000002");
+}
+
+#[test]
+fn requires_the_discord_keys() {
+    for key in ["DISCORD_APP_ID", "DISCORD_PUBLIC_KEY"] {
+        let mut values = required();
+        values.remove(key);
+
+        match Config::from_values(&values) {
+            Err(ConfigError::Missing(missing)) => assert_eq!(missing, key),
+            other => panic!("expected {} to be missing, got {:?}", key, other),
+        }
+    }
+}
+
+#[test]
+fn rejects_empty_values() {
+    let result = Config::from_values(&with(&[("FACTORIO_SAVES_BUCKET", " ")]));
+
+    assert_eq!(invalid_key(result), "FACTORIO_SAVES_BUCKET");
+}
+
+#[test]
+fn normalizes_the_saves_prefix() {
+    let config =
+        Config::from_values(&with(&[("FACTORIO_SAVES_PREFIX", "factorio/saves/")])).unwrap();
+
+    assert_eq!(config.saves_prefix, "factorio/saves/");
+}
+
+#[test]
+fn enables_rcon_with_a_password() {
+    let config = Config::from_values(&with(&[
+        ("FACTORIO_RCON_PASSWORD", "secret"),
+        ("FACTORIO_RCON_PORT", "27016"),
+    ]))
+    .unwrap();
+
+    assert_eq!(config.rcon.unwrap().port, 27016);
+}
+
+#[test]
+fn parses_json_settings() {
+    let config = Config::from_values(&with(&[
+        (
+            "FACTORIO_INSTANCE_SIZES",
+            r#"{"huge": {"instance_type": "c5.4xlarge", "spot_price": "0.40"}}"#,
+        ),
+        ("FACTORIO_BUDGET_THRESHOLDS", "[100, 50, 50]"),
+    ]))
+    .unwrap();
+
+    assert_eq!(config.instance_sizes.keys().collect::<Vec<_>>(), ["huge"]);
+    assert_eq!(config.budget_thresholds, [50, 100]);
+}
+
+#[test]
+fn rejects_invalid_values() {
+    let cases = [
+        ("DISCORD_APP_ID", "not-a-snowflake"),
+        ("DISCORD_PUBLIC_KEY", "abcd"),
+        ("FACTORIO_RCON_PORT", "port"),
+        ("FACTORIO_COMMAND_POLICIES", "{"),
+        ("FACTORIO_INSTANCE_SIZES", "{}"),
+        ("FACTORIO_STOP_COUNTDOWN_SECONDS", "-1"),
+        ("FACTORIO_READY_TIMEOUT_SECONDS", "0"),
+        ("FACTORIO_IDLE_SHUTDOWN_MINUTES", "0"),
+        ("FACTORIO_BUDGET_THRESHOLDS", "[0, 50]"),
+    ];
+
+    for (key, value) in cases {
+        let mut values = with(&[(key, value)]);
+        if key == "FACTORIO_RCON_PORT" {
+            values.insert("FACTORIO_RCON_PASSWORD".to_string(), "secret".to_string());
+        }
+        assert_eq!(invalid_key(Config::from_values(&values)), key, "{}", value);
+    }
+}
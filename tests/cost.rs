@@ -0,0 +1,109 @@
+use factorio_server_lambda::{
+    cost::{month_start, spot_cost, MonthToDate},
+    model::domain::{InstanceCost, Session, SpotPrice},
+};
+use time::{Duration, OffsetDateTime};
+
+/// 2024-03-01 12:00 UTC
+fn noon() -> OffsetDateTime {
+    OffsetDateTime::from_unix_timestamp(1_709_294_400).unwrap()
+}
+
+fn price(timestamp: OffsetDateTime, price: f64) -> SpotPrice {
+    SpotPrice { timestamp, price }
+}
+
+fn assert_cost(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn charges_a_single_price_for_the_whole_window() {
+    let from = noon();
+    let prices = [price(from - Duration::hours(5), 0.10)];
+
+    assert_cost(spot_cost(&prices, from, from + Duration::hours(3)), 0.30);
+}
+
+#[test]
+fn switches_price_when_it_changes() {
+    let from = noon();
+    let prices = [
+        price(from - Duration::hours(1), 0.10),
+        price(from + Duration::hours(1), 0.20),
+        price(from + Duration::minutes(90), 0.05),
+    ];
+
+    // 1h at 0.10, 30m at 0.20, 1h30m at 0.05
+    assert_cost(
+        spot_cost(&prices, from, from + Duration::hours(3)),
+        0.10 + 0.10 + 0.075,
+    );
+}
+
+#[test]
+fn assumes_the_first_price_before_the_history_starts() {
+    let from = noon();
+    let prices = [price(from + Duration::hours(1), 0.10)];
+
+    assert_cost(spot_cost(&prices, from, from + Duration::hours(2)), 0.20);
+}
+
+#[test]
+fn ignores_prices_after_the_window() {
+    let from = noon();
+    let prices = [price(from, 0.10), price(from + Duration::hours(4), 1.00)];
+
+    assert_cost(spot_cost(&prices, from, from + Duration::hours(2)), 0.20);
+}
+
+#[test]
+fn costs_nothing_without_prices_or_time() {
+    let from = noon();
+
+    assert_cost(spot_cost(&[], from, from + Duration::hours(2)), 0.0);
+    assert_cost(spot_cost(&[price(from, 0.10)], from, from), 0.0);
+}
+
+#[test]
+fn counts_an_interrupted_instance_from_a_later_start() {
+    let interrupted = InstanceCost {
+        launched_at: noon() - Duration::hours(3),
+        interrupted_at: noon() + Duration::hours(1),
+        cost: 0.40,
+    };
+
+    assert_cost(interrupted.cost_since(noon() - Duration::hours(5)), 0.40);
+    assert_cost(interrupted.cost_since(noon()), 0.10);
+    assert_cost(interrupted.cost_since(noon() + Duration::hours(2)), 0.0);
+}
+
+#[test]
+fn counts_the_part_of_a_session_after_the_month_started() {
+    // 2024-05-01 00:00 UTC
+    let may = OffsetDateTime::from_unix_timestamp(1_714_521_600).unwrap();
+    let stopped_at = may + Duration::hours(36);
+    let session = Session {
+        server: "factorio".to_string(),
+        started_at: may - Duration::hours(12),
+        started_by: "player".to_string(),
+        save: "world".to_string(),
+        instance_type: None,
+        ready_after: None,
+        stopped_by: Some("player".to_string()),
+        stopped_at: Some(stopped_at),
+        cost: Some(0.80),
+        interrupted: vec![],
+    };
+    let month = MonthToDate {
+        since: month_start(stopped_at).unwrap(),
+        sessions: vec![session],
+        running_cost: None,
+    };
+
+    assert_eq!(month.since, may);
+    assert_cost(month.total(), 0.60);
+}
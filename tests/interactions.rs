@@ -0,0 +1,22 @@
+use factorio_server_lambda::model::{domain::StartServerInteraction, dynamo::DiscordInteraction};
+use time::OffsetDateTime;
+
+fn start(server: &str, interaction_id: &str, timestamp: OffsetDateTime) -> DiscordInteraction {
+    StartServerInteraction {
+        server: server.to_string(),
+        token: format!("token-{}", interaction_id),
+        timestamp,
+        request_token: format!("discord-{}", interaction_id),
+    }
+    .into()
+}
+
+#[test]
+fn starts_in_the_same_second_are_kept_apart() {
+    let now = OffsetDateTime::from_unix_timestamp(1_709_294_400).unwrap();
+    let vanilla = start("vanilla", "1300000000000000001", now);
+    let modded = start("modded", "1300000000000000002", now);
+
+    assert_eq!(vanilla.timestamp, modded.timestamp);
+    assert_ne!(vanilla.key(), modded.key());
+}
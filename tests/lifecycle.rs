@@ -0,0 +1,200 @@
+use factorio_server_lambda::model::domain::{
+    InstanceStatus, ServerLifecycle, ServiceStatus, StackStatus,
+};
+
+use ServerLifecycle::*;
+
+const ALL: [ServerLifecycle; 6] = [Stopped, Starting, Running, Stopping, Failed, Unknown];
+
+fn stack(status: &str, server_state: Option<&str>) -> StackStatus {
+    StackStatus {
+        status: status.to_string(),
+        server_state: server_state.map(|state| state.to_string()),
+        ..Default::default()
+    }
+}
+
+fn instance(lifecycle: &str) -> InstanceStatus {
+    InstanceStatus {
+        instance_id: "i-0123456789abcdef0".to_string(),
+        lifecycle: lifecycle.to_string(),
+        ip: Some("203.0.113.7".to_string()),
+        launched_at: None,
+    }
+}
+
+fn service(running: i32) -> ServiceStatus {
+    ServiceStatus {
+        rollout: Some("COMPLETED".to_string()),
+        desired: 1,
+        running,
+        pending: 0,
+        task: None,
+    }
+}
+
+fn derive(
+    stack: &StackStatus,
+    instance: Option<&InstanceStatus>,
+    service: Option<&ServiceStatus>,
+) -> ServerLifecycle {
+    ServerLifecycle::derive(stack, instance, service)
+}
+
+#[test]
+fn running_needs_an_instance_in_service_and_a_task() {
+    let stack = stack("UPDATE_COMPLETE", Some("Running"));
+    let in_service = instance("InService");
+
+    assert_eq!(
+        derive(&stack, Some(&in_service), Some(&service(1))),
+        Running
+    );
+    assert_eq!(
+        derive(&stack, Some(&in_service), Some(&service(0))),
+        Starting
+    );
+    assert_eq!(derive(&stack, Some(&in_service), None), Starting);
+    assert_eq!(
+        derive(&stack, Some(&instance("Pending")), Some(&service(1))),
+        Starting
+    );
+    assert_eq!(derive(&stack, None, Some(&service(0))), Starting);
+}
+
+#[test]
+fn starting_while_the_stack_updates() {
+    let stack = stack("UPDATE_IN_PROGRESS", Some("Running"));
+
+    assert_eq!(derive(&stack, None, Some(&service(0))), Starting);
+    assert_eq!(
+        derive(&stack, Some(&instance("InService")), Some(&service(1))),
+        Running
+    );
+}
+
+#[test]
+fn stopped_once_the_instance_and_task_are_gone() {
+    let stack = stack("UPDATE_COMPLETE", Some("Stopped"));
+
+    assert_eq!(derive(&stack, None, Some(&service(0))), Stopped);
+    assert_eq!(derive(&stack, None, None), Stopped);
+    assert_eq!(
+        derive(&stack, Some(&instance("Terminated")), Some(&service(0))),
+        Stopped
+    );
+}
+
+#[test]
+fn stopping_while_anything_is_left() {
+    let stack = stack("UPDATE_IN_PROGRESS", Some("Stopped"));
+
+    assert_eq!(
+        derive(&stack, Some(&instance("InService")), Some(&service(1))),
+        Stopping
+    );
+    assert_eq!(
+        derive(&stack, Some(&instance("Terminating")), Some(&service(0))),
+        Stopping
+    );
+    assert_eq!(derive(&stack, None, Some(&service(1))), Stopping);
+}
+
+#[test]
+fn failed_stack_operations() {
+    let in_service = instance("InService");
+    for status in [
+        "CREATE_FAILED",
+        "UPDATE_FAILED",
+        "UPDATE_ROLLBACK_FAILED",
+        "ROLLBACK_IN_PROGRESS",
+        "ROLLBACK_COMPLETE",
+        "UPDATE_ROLLBACK_IN_PROGRESS",
+        "UPDATE_ROLLBACK_COMPLETE_CLEANUP_IN_PROGRESS",
+    ] {
+        assert_eq!(
+            derive(
+                &stack(status, Some("Running")),
+                Some(&in_service),
+                Some(&service(1))
+            ),
+            Failed,
+            "{}",
+            status
+        );
+    }
+}
+
+#[test]
+fn rolled_back_updates_follow_the_resources() {
+    let stack = stack("UPDATE_ROLLBACK_COMPLETE", Some("Stopped"));
+
+    assert_eq!(derive(&stack, None, Some(&service(0))), Stopped);
+    assert_eq!(
+        derive(&stack, Some(&instance("InService")), Some(&service(1))),
+        Stopping
+    );
+}
+
+#[test]
+fn unmanaged_stacks_are_unknown() {
+    for status in [
+        "",
+        "DELETE_IN_PROGRESS",
+        "DELETE_COMPLETE",
+        "IMPORT_IN_PROGRESS",
+    ] {
+        assert_eq!(
+            derive(&stack(status, Some("Running")), None, None),
+            Unknown,
+            "{:?}",
+            status
+        );
+    }
+    assert_eq!(derive(&stack("UPDATE_COMPLETE", None), None, None), Unknown);
+    assert_eq!(
+        derive(&stack("UPDATE_COMPLETE", Some("Paused")), None, None),
+        Unknown
+    );
+}
+
+#[test]
+fn legal_transitions() {
+    let legal = [
+        (Stopped, Starting),
+        (Starting, Running),
+        (Starting, Stopping),
+        (Starting, Failed),
+        (Running, Starting),
+        (Running, Stopping),
+        (Running, Failed),
+        (Stopping, Stopped),
+        (Stopping, Failed),
+        (Failed, Stopped),
+        (Failed, Starting),
+        (Failed, Running),
+        (Failed, Stopping),
+    ];
+
+    for from in ALL {
+        for to in ALL {
+            let expected =
+                from == to || from == Unknown || to == Unknown || legal.contains(&(from, to));
+            assert_eq!(
+                from.can_transition_to(to),
+                expected,
+                "{:?} -> {:?}",
+                from,
+                to
+            );
+        }
+    }
+}
+
+#[test]
+fn commands_can_not_skip_states() {
+    assert!(!Stopped.can_transition_to(Stopping));
+    assert!(!Stopped.can_transition_to(Running));
+    assert!(!Stopping.can_transition_to(Starting));
+    assert!(!Stopping.can_transition_to(Running));
+}
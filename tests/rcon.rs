@@ -0,0 +1,281 @@
+use std::{collections::HashMap, time::Duration};
+
+use factorio_server_lambda::{
+    model::domain::OnlinePlayer,
+    rcon::{
+        packet::{
+            Packet, SERVERDATA_AUTH, SERVERDATA_AUTH_RESPONSE, SERVERDATA_EXECCOMMAND,
+            SERVERDATA_RESPONSE_VALUE,
+        },
+        parse_players, RconClient, RconError,
+    },
+};
+use tokio::net::TcpListener;
+
+const PASSWORD: &str = "hunter2";
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A fake factorio RCON server answering a fixed set of commands.
+struct FakeServer {
+    responses: HashMap<String, String>,
+    /// Responses longer than this are split over several packets.
+    chunk_size: usize,
+    /// Stop answering commands, to exercise timeouts.
+    unresponsive: bool,
+}
+
+impl FakeServer {
+    fn new(responses: &[(&str, &str)]) -> Self {
+        FakeServer {
+            responses: responses
+                .iter()
+                .map(|(command, response)| (command.to_string(), response.to_string()))
+                .collect(),
+            chunk_size: 4096,
+            unresponsive: false,
+        }
+    }
+
+    /// Starts serving a single connection, returning the address to connect to.
+    async fn spawn(self) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            while let Ok(request) = Packet::read_from(&mut socket).await {
+                match request.kind {
+                    SERVERDATA_AUTH => {
+                        let id = if request.body == PASSWORD {
+                            request.id
+                        } else {
+                            -1
+                        };
+                        Packet::new(request.id, SERVERDATA_RESPONSE_VALUE, "")
+                            .write_to(&mut socket)
+                            .await
+                            .unwrap();
+                        Packet::new(id, SERVERDATA_AUTH_RESPONSE, "")
+                            .write_to(&mut socket)
+                            .await
+                            .unwrap();
+                    }
+                    SERVERDATA_EXECCOMMAND if self.unresponsive => {}
+                    SERVERDATA_EXECCOMMAND => {
+                        let response = self
+                            .responses
+                            .get(&request.body)
+                            .cloned()
+                            .unwrap_or_default();
+                        let chunks: Vec<String> = response
+                            .as_bytes()
+                            .chunks(self.chunk_size)
+                            .map(|chunk| String::from_utf8(chunk.to_vec()).unwrap())
+                            .collect();
+                        if chunks.is_empty() {
+                            Packet::new(request.id, SERVERDATA_RESPONSE_VALUE, "")
+                                .write_to(&mut socket)
+                                .await
+                                .unwrap();
+                        }
+                        for chunk in chunks {
+                            Packet::new(request.id, SERVERDATA_RESPONSE_VALUE, &chunk)
+                                .write_to(&mut socket)
+                                .await
+                                .unwrap();
+                        }
+                    }
+                    other => panic!("unexpected packet type {}", other),
+                }
+            }
+        });
+
+        addr
+    }
+}
+
+#[tokio::test]
+async fn runs_commands_after_authenticating() {
+    let addr = FakeServer::new(&[("/version", "1.1.101")]).spawn().await;
+
+    let mut client = RconClient::connect(addr, PASSWORD, TIMEOUT).await.unwrap();
+
+    assert_eq!(client.exec("/version").await.unwrap(), "1.1.101");
+}
+
+#[tokio::test]
+async fn rejects_wrong_password() {
+    let addr = FakeServer::new(&[]).spawn().await;
+
+    let result = RconClient::connect(addr, "wrong", TIMEOUT).await;
+
+    assert!(matches!(result, Err(RconError::AuthFailed)));
+}
+
+#[tokio::test]
+async fn joins_multi_packet_responses() {
+    let long_output = "x".repeat(10_000);
+    let mut server = FakeServer::new(&[("/long", &long_output)]);
+    server.chunk_size = 4000;
+    let addr = server.spawn().await;
+
+    let mut client = RconClient::connect(addr, PASSWORD, TIMEOUT).await.unwrap();
+
+    assert_eq!(client.exec("/long").await.unwrap(), long_output);
+    // The connection is still usable after a split response
+    assert_eq!(client.exec("/unknown").await.unwrap(), "");
+}
+
+#[tokio::test]
+async fn lists_online_players() {
+    let addr = FakeServer::new(&[(
+        "/players online",
+        "Online players (2):\n  alice (online)\n  bob (online)\n",
+    )])
+    .spawn()
+    .await;
+
+    let mut client = RconClient::connect(addr, PASSWORD, TIMEOUT).await.unwrap();
+
+    assert_eq!(client.players_online().await.unwrap(), ["alice", "bob"]);
+}
+
+#[tokio::test]
+async fn counts_players_without_running_lua() {
+    let addr = FakeServer::new(&[(
+        "/players",
+        "Players (3):\n  alice (online)\n  bob\n  carol (online)\n",
+    )])
+    .spawn()
+    .await;
+
+    let mut client = RconClient::connect(addr, PASSWORD, TIMEOUT).await.unwrap();
+    let (total, online) = client.online_players().await.unwrap();
+
+    assert_eq!(total, 3);
+    assert_eq!(
+        online,
+        [
+            OnlinePlayer {
+                name: "alice".to_string(),
+                online_time: None,
+            },
+            OnlinePlayer {
+                name: "carol".to_string(),
+                online_time: None,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn lists_online_players_with_play_time() {
+    let addr = FakeServer::new(&[(
+        "/silent-command rcon.print(#game.players) for _, p in pairs(game.connected_players) do rcon.print(p.name .. ' ' .. p.online_time) end",
+        "5\nalice 216000\nbob 3600\n",
+    )])
+    .spawn()
+    .await;
+
+    let mut client = RconClient::connect(addr, PASSWORD, TIMEOUT).await.unwrap();
+    let (total, online) = client.online_players_with_play_time().await.unwrap();
+
+    assert_eq!(total, 5);
+    assert_eq!(
+        online,
+        [
+            OnlinePlayer {
+                name: "alice".to_string(),
+                online_time: Some(time::Duration::hours(1)),
+            },
+            OnlinePlayer {
+                name: "bob".to_string(),
+                online_time: Some(time::Duration::minutes(1)),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn sends_save_and_silent_commands() {
+    let addr = FakeServer::new(&[
+        (
+            "/server-save",
+            "Saving map to /factorio/saves/_autosave1.zip",
+        ),
+        ("/silent-command rcon.print(game.tick)", "1234"),
+    ])
+    .spawn()
+    .await;
+
+    let mut client = RconClient::connect(addr, PASSWORD, TIMEOUT).await.unwrap();
+
+    assert_eq!(
+        client.server_save().await.unwrap(),
+        "Saving map to /factorio/saves/_autosave1.zip"
+    );
+    assert_eq!(
+        client
+            .silent_command("rcon.print(game.tick)")
+            .await
+            .unwrap(),
+        "1234"
+    );
+}
+
+#[tokio::test]
+async fn waits_for_the_save_to_finish() {
+    let addr = FakeServer::new(&[
+        (
+            "/server-save",
+            "Saving map to /factorio/saves/_autosave1.zip",
+        ),
+        ("/version", "1.1.101"),
+    ])
+    .spawn()
+    .await;
+
+    let mut client = RconClient::connect(addr, PASSWORD, TIMEOUT).await.unwrap();
+
+    assert_eq!(
+        client.server_save_and_wait().await.unwrap(),
+        "Saving map to /factorio/saves/_autosave1.zip"
+    );
+    client.broadcast("/shutting down").await.unwrap();
+    assert_eq!(client.exec("/version").await.unwrap(), "1.1.101");
+}
+
+#[tokio::test]
+async fn times_out_when_the_server_does_not_answer() {
+    let mut server = FakeServer::new(&[]);
+    server.unresponsive = true;
+    let addr = server.spawn().await;
+
+    let mut client = RconClient::connect(addr, PASSWORD, TIMEOUT).await.unwrap();
+
+    assert!(matches!(
+        client.exec("/players").await,
+        Err(RconError::Timeout)
+    ));
+}
+
+#[tokio::test]
+async fn rejects_oversized_commands() {
+    let addr = FakeServer::new(&[]).spawn().await;
+
+    let mut client = RconClient::connect(addr, PASSWORD, TIMEOUT).await.unwrap();
+
+    assert!(matches!(
+        client.exec(&"x".repeat(5000)).await,
+        Err(RconError::CommandTooLong)
+    ));
+}
+
+#[test]
+fn parses_players_without_online_marker() {
+    assert_eq!(
+        parse_players("Players (3):\n  alice (online)\n  bob\n  carol\n"),
+        ["alice", "bob", "carol"]
+    );
+    assert!(parse_players("Online players (0):\n").is_empty());
+}
@@ -0,0 +1,137 @@
+use factorio_server_lambda::{
+    commands::CommandRegistry,
+    discord::api::{CommandScope, DiscordApi},
+};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// A request as seen by the mock server.
+struct RecordedRequest {
+    request_line: String,
+    headers: Vec<(String, String)>,
+    body: Value,
+}
+
+impl RecordedRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Serves a single HTTP request with the given status, returning the base URL
+/// to call and a handle resolving to the request that was received.
+async fn mock_discord(status: u16) -> (String, tokio::task::JoinHandle<RecordedRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}/api/v10", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        let mut raw = vec![];
+        let mut buf = [0; 4096];
+        let (head, body) = loop {
+            let read = socket.read(&mut buf).await.unwrap();
+            raw.extend_from_slice(&buf[..read]);
+            let text = String::from_utf8_lossy(&raw).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| {
+                        let (key, value) = line.split_once(':')?;
+                        key.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    break (head.to_string(), body.to_string());
+                }
+            }
+        };
+
+        let response = format!("HTTP/1.1 {} OK\r\ncontent-length: 2\r\n\r\n[]", status);
+        socket.write_all(response.as_bytes()).await.unwrap();
+
+        let mut lines = head.lines();
+        RecordedRequest {
+            request_line: lines.next().unwrap().to_string(),
+            headers: lines
+                .filter_map(|line| line.split_once(':'))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect(),
+            body: serde_json::from_str(&body).unwrap(),
+        }
+    });
+
+    (base_url, handle)
+}
+
+#[tokio::test]
+async fn registers_guild_commands() {
+    let (base_url, request) = mock_discord(200).await;
+    let registry = CommandRegistry::new();
+
+    DiscordApi::new(&base_url, "1234", "secret")
+        .register_commands(
+            &CommandScope::Guild("5678".to_string()),
+            &[registry.definition()],
+        )
+        .await
+        .unwrap();
+
+    let request = request.await.unwrap();
+    assert_eq!(
+        request.request_line,
+        "PUT /api/v10/applications/1234/guilds/5678/commands HTTP/1.1"
+    );
+    assert_eq!(request.header("authorization"), Some("Bot secret"));
+    assert_eq!(request.body[0]["name"], "factorio");
+
+    let subcommands: Vec<&str> = request.body[0]["options"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|option| option["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        subcommands,
+        ["start", "stop", "ip", "players", "status", "history", "cost", "budget"]
+    );
+}
+
+#[tokio::test]
+async fn registers_global_commands() {
+    let (base_url, request) = mock_discord(200).await;
+
+    DiscordApi::new(&base_url, "1234", "secret")
+        .register_commands(
+            &CommandScope::Global,
+            &[CommandRegistry::new().definition()],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        request.await.unwrap().request_line,
+        "PUT /api/v10/applications/1234/commands HTTP/1.1"
+    );
+}
+
+#[tokio::test]
+async fn reports_rejected_registration() {
+    let (base_url, _request) = mock_discord(400).await;
+
+    let result = DiscordApi::new(&base_url, "1234", "secret")
+        .register_commands(
+            &CommandScope::Global,
+            &[CommandRegistry::new().definition()],
+        )
+        .await;
+
+    assert!(result.is_err());
+}
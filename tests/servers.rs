@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use factorio_server_lambda::config::Config;
+
+/// The public key of RFC 8032's first ed25519 test vector.
+const PUBLIC_KEY: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+fn config(pairs: &[(&str, &str)]) -> Config {
+    let values: HashMap<String, String> = [
+        ("DISCORD_APP_ID", "1400000000000000001"),
+        ("DISCORD_PUBLIC_KEY", PUBLIC_KEY),
+    ]
+    .iter()
+    .chain(pairs)
+    .map(|(key, value)| (key.to_string(), value.to_string()))
+    .collect();
+    Config::from_values(&values).unwrap()
+}
+
+#[test]
+fn falls_back_to_the_template_stack() {
+    let config = config(&[]);
+
+    assert_eq!(config.default_server, "factorio");
+    assert_eq!(config.fallback_server.name, "factorio");
+    assert_eq!(config.fallback_server.stack_name, "factorio-ecs-spot");
+    assert_eq!(
+        config.fallback_server.cluster_name,
+        "factorio-ecs-spot-cluster"
+    );
+    assert_eq!(
+        config.fallback_server.service_name,
+        "factorio-ecs-spot-ecs-service"
+    );
+    assert_eq!(config.fallback_server.asg_name, "factorio-ecs-spot-asg");
+}
+
+#[test]
+fn derives_the_fallback_server_from_the_stack_name() {
+    let config = config(&[
+        ("FACTORIO_DEFAULT_SERVER", "vanilla"),
+        ("FACTORIO_STACK_NAME", "my-stack"),
+        ("FACTORIO_ASG_NAME", "custom-asg"),
+    ]);
+
+    assert_eq!(config.fallback_server.name, "vanilla");
+    assert_eq!(config.fallback_server.stack_name, "my-stack");
+    assert_eq!(config.fallback_server.cluster_name, "my-stack-cluster");
+    assert_eq!(config.fallback_server.asg_name, "custom-asg");
+}